@@ -1,5 +1,5 @@
 use super::verify_file;
-use crate::{process, CmdExecutor};
+use crate::{get_binary_writer, get_reader, get_writer, process, CmdExecutor};
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 use std::{fmt, str::FromStr};
@@ -11,20 +11,38 @@ pub enum Base64SubCommand {
     Encode(EncodeOpts),
     #[command(name = "decode", about = "Decode a base64 file")]
     Decode(DecodeOpts),
+    #[command(
+        name = "detect",
+        about = "Report which encodings a blob is valid under"
+    )]
+    Detect(DetectOpts),
+    #[command(name = "datauri", about = "Convert a file to or from a data: URI")]
+    DataUri(DataUriOpts),
+    #[command(name = "pem", about = "Wrap or unwrap PEM armor")]
+    Pem(PemOpts),
 }
 
 #[derive(Parser, Debug)]
 pub struct EncodeOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
     #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Wrap lines at this width, e.g. 76 (MIME) or 64 (PEM)"
+    )]
+    pub wrap: usize,
 }
 
 impl CmdExecutor for EncodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let result = process::process_base64_encode(&self.input, self.format)?;
-        println!("{}", result);
+        let mut reader = get_reader(&self.input)?;
+        let mut writer = get_writer(&self.output)?;
+        process::process_base64_encode(&mut reader, &mut writer, self.format, self.wrap)?;
         Ok(())
     }
 }
@@ -33,14 +51,109 @@ impl CmdExecutor for EncodeOpts {
 pub struct DecodeOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
     #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
 }
 
 impl CmdExecutor for DecodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let result = process::process_base64_decode(&self.input, self.format)?;
-        println!("{}", String::from_utf8(result)?);
+        let mut reader = get_reader(&self.input)?;
+        let mut writer = get_binary_writer(&self.output, self.force)?;
+        process::process_base64_decode(&mut reader, &mut writer, self.format)?;
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct DetectOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+}
+
+impl CmdExecutor for DetectOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut reader = get_reader(&self.input)?;
+        let detected = process::process_base64_detect(&mut reader)?;
+        if detected.is_empty() {
+            println!("No matching encoding");
+        }
+        for (format, len) in detected {
+            println!("{}: {} bytes", format, len);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct DataUriOpts {
+    #[arg(value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(
+        long,
+        conflicts_with = "decode",
+        help = "Media type, sniffed from the content by default"
+    )]
+    pub mime: Option<String>,
+    #[arg(short, long, help = "Parse a data URI back into its payload")]
+    pub decode: bool,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
+}
+
+impl CmdExecutor for DataUriOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut reader = get_reader(&self.input)?;
+        if self.decode {
+            let mut writer = get_binary_writer(&self.output, self.force)?;
+            let mime = process::process_datauri_decode(&mut reader, &mut writer)?;
+            eprintln!("Media type: {}", mime);
+        } else {
+            let mut writer = get_writer(&self.output)?;
+            process::process_datauri_encode(&mut reader, &mut writer, self.mime.as_deref())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct PemOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(
+        long,
+        required_unless_present = "decode",
+        help = "PEM label, e.g. \"PUBLIC KEY\"; filters blocks when decoding"
+    )]
+    pub label: Option<String>,
+    #[arg(short, long, help = "Decode every PEM block in the input")]
+    pub decode: bool,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
+}
+
+impl CmdExecutor for PemOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut reader = get_reader(&self.input)?;
+        if self.decode {
+            let mut writer = get_binary_writer(&self.output, self.force)?;
+            let blocks =
+                process::process_pem_decode(&mut reader, &mut writer, self.label.as_deref())?;
+            for (label, len) in blocks {
+                eprintln!("{}: {} bytes", label, len);
+            }
+        } else {
+            let label = self.label.as_deref().unwrap_or_default();
+            let mut writer = get_writer(&self.output)?;
+            process::process_pem_encode(&mut reader, &mut writer, label)?;
+        }
         Ok(())
     }
 }
@@ -49,6 +162,7 @@ impl CmdExecutor for DecodeOpts {
 pub enum Base64Format {
     Standard,
     UrlSafe,
+    Auto,
 }
 
 pub fn parse_base64_format(format: &str) -> Result<Base64Format, anyhow::Error> {
@@ -62,6 +176,7 @@ impl FromStr for Base64Format {
         match value {
             "standard" => Ok(Base64Format::Standard),
             "urlsafe" => Ok(Base64Format::UrlSafe),
+            "auto" => Ok(Base64Format::Auto),
             v => anyhow::bail!("Unsupported format: {}", v),
         }
     }
@@ -72,6 +187,7 @@ impl From<Base64Format> for &'static str {
         match format {
             Base64Format::Standard => "standard",
             Base64Format::UrlSafe => "urlsafe",
+            Base64Format::Auto => "auto",
         }
     }
 }
@@ -0,0 +1,138 @@
+use super::verify_file;
+use crate::{get_binary_writer, get_reader, get_writer, process, CmdExecutor};
+use clap::Parser;
+use std::{fmt, str::FromStr};
+
+#[derive(Parser, Debug)]
+pub struct CodecEncodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(short, long, value_parser = parse_codec_format, default_value = "base64")]
+    pub codec: CodecFormat,
+    #[arg(long, default_value_t = 0, help = "Wrap lines at this width")]
+    pub wrap: usize,
+}
+
+impl CmdExecutor for CodecEncodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut reader = get_reader(&self.input)?;
+        let mut writer = get_writer(&self.output)?;
+        process::process_encode(&mut reader, &mut writer, self.codec, self.wrap)?;
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CodecDecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(short, long, value_parser = parse_codec_format, default_value = "base64")]
+    pub codec: CodecFormat,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
+}
+
+impl CmdExecutor for CodecDecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut reader = get_reader(&self.input)?;
+        let mut writer = get_binary_writer(&self.output, self.force)?;
+        process::process_decode(&mut reader, &mut writer, self.codec)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CodecFormat {
+    Base16,
+    Base32,
+    Base32Hex,
+    Base32Crockford,
+    Base58,
+    Base58Flickr,
+    Base62,
+    Ascii85,
+    Z85,
+    Base85,
+    Base64,
+    Base64NoPad,
+    Base64Url,
+    Base64UrlNoPad,
+}
+
+impl CodecFormat {
+    pub const ALL: [CodecFormat; 14] = [
+        CodecFormat::Base16,
+        CodecFormat::Base32,
+        CodecFormat::Base32Hex,
+        CodecFormat::Base32Crockford,
+        CodecFormat::Base58,
+        CodecFormat::Base58Flickr,
+        CodecFormat::Base62,
+        CodecFormat::Ascii85,
+        CodecFormat::Z85,
+        CodecFormat::Base85,
+        CodecFormat::Base64,
+        CodecFormat::Base64NoPad,
+        CodecFormat::Base64Url,
+        CodecFormat::Base64UrlNoPad,
+    ];
+}
+
+pub fn parse_codec_format(format: &str) -> Result<CodecFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for CodecFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "base16" | "hex" => Ok(CodecFormat::Base16),
+            "base32" => Ok(CodecFormat::Base32),
+            "base32hex" => Ok(CodecFormat::Base32Hex),
+            "base32-crockford" | "crockford" => Ok(CodecFormat::Base32Crockford),
+            "base58" | "base58-bitcoin" => Ok(CodecFormat::Base58),
+            "base58-flickr" => Ok(CodecFormat::Base58Flickr),
+            "base62" => Ok(CodecFormat::Base62),
+            "ascii85" => Ok(CodecFormat::Ascii85),
+            "z85" => Ok(CodecFormat::Z85),
+            "base85" => Ok(CodecFormat::Base85),
+            "base64" => Ok(CodecFormat::Base64),
+            "base64-nopad" => Ok(CodecFormat::Base64NoPad),
+            "base64url" => Ok(CodecFormat::Base64Url),
+            "base64url-nopad" => Ok(CodecFormat::Base64UrlNoPad),
+            v => anyhow::bail!("Unsupported codec: {}", v),
+        }
+    }
+}
+
+impl From<CodecFormat> for &'static str {
+    fn from(format: CodecFormat) -> Self {
+        match format {
+            CodecFormat::Base16 => "base16",
+            CodecFormat::Base32 => "base32",
+            CodecFormat::Base32Hex => "base32hex",
+            CodecFormat::Base32Crockford => "base32-crockford",
+            CodecFormat::Base58 => "base58",
+            CodecFormat::Base58Flickr => "base58-flickr",
+            CodecFormat::Base62 => "base62",
+            CodecFormat::Ascii85 => "ascii85",
+            CodecFormat::Z85 => "z85",
+            CodecFormat::Base85 => "base85",
+            CodecFormat::Base64 => "base64",
+            CodecFormat::Base64NoPad => "base64-nopad",
+            CodecFormat::Base64Url => "base64url",
+            CodecFormat::Base64UrlNoPad => "base64url-nopad",
+        }
+    }
+}
+
+impl fmt::Display for CodecFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
@@ -0,0 +1,154 @@
+use super::verify_file;
+use crate::{get_binary_writer, get_reader, get_writer, process, CmdExecutor};
+use clap::Parser;
+use std::{fmt, str::FromStr};
+
+#[derive(Parser, Debug)]
+pub struct CompressOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-", conflicts_with = "sibling")]
+    pub output: String,
+    #[arg(short, long, value_parser = parse_compress_algorithm, default_value = "gzip")]
+    pub algorithm: CompressAlgorithm,
+    #[arg(
+        short,
+        long,
+        help = "Compression level, defaults to the algorithm's usual level"
+    )]
+    pub level: Option<u32>,
+    #[arg(long, help = "Base64 encode the compressed output")]
+    pub base64: bool,
+    #[arg(
+        long,
+        help = "Write next to the input as a precompressed sibling (.gz, .zz, .zst, .br, .xz)"
+    )]
+    pub sibling: bool,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
+}
+
+impl CmdExecutor for CompressOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let output = if self.sibling {
+            if self.input == "-" {
+                anyhow::bail!("--sibling needs an input file");
+            }
+            format!("{}.{}", self.input, self.algorithm.extension())
+        } else {
+            self.output
+        };
+
+        let mut reader = get_reader(&self.input)?;
+        if self.base64 {
+            let mut writer = get_writer(&output)?;
+            process::process_compress_base64(&mut reader, &mut writer, self.algorithm, self.level)?;
+        } else {
+            let mut writer = get_binary_writer(&output, self.force)?;
+            process::process_compress(&mut reader, &mut writer, self.algorithm, self.level)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct DecompressOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(short, long, value_parser = parse_compress_algorithm, help = "Detected from magic bytes, or a .br extension, by default")]
+    pub algorithm: Option<CompressAlgorithm>,
+    #[arg(long, help = "Base64 decode the input first")]
+    pub base64: bool,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
+}
+
+impl CmdExecutor for DecompressOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        // brotli streams have no magic bytes, so go by the file name
+        let algorithm = self.algorithm.or_else(|| {
+            self.input
+                .ends_with(".br")
+                .then_some(CompressAlgorithm::Brotli)
+        });
+        let mut reader = get_reader(&self.input)?;
+        let mut writer = get_binary_writer(&self.output, self.force)?;
+        if self.base64 {
+            process::process_decompress_base64(&mut reader, &mut writer, algorithm)?;
+        } else {
+            process::process_decompress(&mut reader, &mut writer, algorithm)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CompressAlgorithm {
+    Gzip,
+    Deflate,
+    Zstd,
+    Brotli,
+    Xz,
+}
+
+impl CompressAlgorithm {
+    /// File extension of the precompressed sibling, as looked up by `ServeDir`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CompressAlgorithm::Gzip => "gz",
+            CompressAlgorithm::Deflate => "zz",
+            CompressAlgorithm::Zstd => "zst",
+            CompressAlgorithm::Brotli => "br",
+            CompressAlgorithm::Xz => "xz",
+        }
+    }
+
+    /// Minimum, maximum and default compression level
+    pub fn levels(&self) -> (u32, u32, u32) {
+        match self {
+            CompressAlgorithm::Gzip | CompressAlgorithm::Deflate => (0, 9, 6),
+            CompressAlgorithm::Zstd => (1, 22, 3),
+            CompressAlgorithm::Brotli => (0, 11, 11),
+            CompressAlgorithm::Xz => (0, 9, 6),
+        }
+    }
+}
+
+pub fn parse_compress_algorithm(algorithm: &str) -> Result<CompressAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl FromStr for CompressAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "gzip" | "gz" => Ok(CompressAlgorithm::Gzip),
+            "deflate" | "zlib" => Ok(CompressAlgorithm::Deflate),
+            "zstd" | "zst" => Ok(CompressAlgorithm::Zstd),
+            "brotli" | "br" => Ok(CompressAlgorithm::Brotli),
+            "xz" => Ok(CompressAlgorithm::Xz),
+            v => anyhow::bail!("Unsupported algorithm: {}", v),
+        }
+    }
+}
+
+impl From<CompressAlgorithm> for &'static str {
+    fn from(algorithm: CompressAlgorithm) -> Self {
+        match algorithm {
+            CompressAlgorithm::Gzip => "gzip",
+            CompressAlgorithm::Deflate => "deflate",
+            CompressAlgorithm::Zstd => "zstd",
+            CompressAlgorithm::Brotli => "brotli",
+            CompressAlgorithm::Xz => "xz",
+        }
+    }
+}
+
+impl fmt::Display for CompressAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{process, CmdExecutor};
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::{fmt, str::FromStr};
+
+const NANOID_ALPHABET: &str = "_-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+// 2010-11-04T01:42:54.657Z, the epoch used by Twitter snowflakes
+const SNOWFLAKE_EPOCH: u64 = 1288834974657;
+
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum GenSubCommand {
+    #[command(about = "Generate identifiers or random tokens")]
+    Id(GenIdOpts),
+    #[command(about = "Decode the timestamp inside a UUID v7, ULID or Snowflake id")]
+    Inspect(GenInspectOpts),
+}
+
+#[derive(Parser, Debug)]
+pub struct GenIdOpts {
+    #[arg(short, long, value_parser = parse_id_kind, default_value = "uuid4")]
+    pub kind: IdKind,
+    #[arg(short, default_value_t = 1, help = "Number of ids to generate")]
+    pub n: usize,
+    #[arg(short, long, help = "Nanoid length or number of random bytes")]
+    pub size: Option<usize>,
+    #[arg(long, default_value = NANOID_ALPHABET, help = "Nanoid alphabet")]
+    pub alphabet: String,
+    #[arg(long, default_value_t = 0, help = "Snowflake worker id")]
+    pub worker: u16,
+    #[arg(long, default_value_t = SNOWFLAKE_EPOCH, help = "Snowflake epoch in unix milliseconds")]
+    pub epoch: u64,
+}
+
+impl CmdExecutor for GenIdOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let ids = process::process_gen_id(
+            self.kind,
+            self.n,
+            self.size,
+            &self.alphabet,
+            self.worker,
+            self.epoch,
+        )?;
+        for id in ids {
+            println!("{}", id);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct GenInspectOpts {
+    pub id: String,
+    #[arg(long, default_value_t = SNOWFLAKE_EPOCH, help = "Snowflake epoch in unix milliseconds")]
+    pub epoch: u64,
+}
+
+impl CmdExecutor for GenInspectOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        for (name, value) in process::process_inspect_id(&self.id, self.epoch)? {
+            println!("{}: {}", name, value);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum IdKind {
+    Uuid4,
+    Uuid7,
+    Ulid,
+    Nanoid,
+    Snowflake,
+    Hex,
+    Base64,
+    Base32,
+}
+
+pub fn parse_id_kind(kind: &str) -> Result<IdKind, anyhow::Error> {
+    kind.parse()
+}
+
+impl FromStr for IdKind {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "uuid4" | "uuid" => Ok(IdKind::Uuid4),
+            "uuid7" => Ok(IdKind::Uuid7),
+            "ulid" => Ok(IdKind::Ulid),
+            "nanoid" => Ok(IdKind::Nanoid),
+            "snowflake" => Ok(IdKind::Snowflake),
+            "hex" => Ok(IdKind::Hex),
+            "base64" => Ok(IdKind::Base64),
+            "base32" => Ok(IdKind::Base32),
+            v => anyhow::bail!("Unsupported kind: {}", v),
+        }
+    }
+}
+
+impl From<IdKind> for &'static str {
+    fn from(kind: IdKind) -> Self {
+        match kind {
+            IdKind::Uuid4 => "uuid4",
+            IdKind::Uuid7 => "uuid7",
+            IdKind::Ulid => "ulid",
+            IdKind::Nanoid => "nanoid",
+            IdKind::Snowflake => "snowflake",
+            IdKind::Hex => "hex",
+            IdKind::Base64 => "base64",
+            IdKind::Base32 => "base32",
+        }
+    }
+}
+
+impl fmt::Display for IdKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
@@ -1,9 +1,18 @@
-use crate::{process, CmdExecutor};
-use clap::Parser;
+use super::verify_file;
+use crate::{get_reader, process, process::MasterSecret, CmdExecutor, KeyLoader};
+use clap::{ArgAction, Parser};
+use enum_dispatch::enum_dispatch;
+use std::{fmt, io::Read, str::FromStr};
 use zxcvbn::zxcvbn;
 
+const MAX_BREACHED_ATTEMPTS: usize = 100;
+
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct GenPassOpts {
+    #[command(subcommand)]
+    pub cmd: Option<GenPassSubCommand>,
+
     #[arg(short, long, default_value_t = 16)]
     pub length: u8,
 
@@ -18,11 +27,122 @@ pub struct GenPassOpts {
 
     #[arg(long, default_value_t = true)]
     pub symbol: bool,
+
+    #[arg(long, value_parser = verify_file, help = "Regenerate while the password is in this Pwned Passwords dataset")]
+    pub reject_breached: Option<String>,
 }
 
 impl CmdExecutor for GenPassOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let password = process::process_gen_pass(
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+
+        let mut attempts = 0;
+        let password = loop {
+            let password = process::process_gen_pass(
+                self.length,
+                self.upper,
+                self.lower,
+                self.number,
+                self.symbol,
+            )?;
+            let Some(dataset) = &self.reject_breached else {
+                break password;
+            };
+            let status = process::process_breached_check(&password, Some(dataset), None)?;
+            if status == process::BreachedStatus::NotFound {
+                break password;
+            }
+            attempts += 1;
+            if attempts >= MAX_BREACHED_ATTEMPTS {
+                anyhow::bail!("Could not generate a password absent from the dataset");
+            }
+        };
+        println!("{}", password);
+
+        let estimate = zxcvbn(&password, &[])?;
+        eprintln!("Estimated strength: {}", estimate.score());
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum GenPassSubCommand {
+    #[command(about = "Derive a site specific password from a master secret")]
+    Derive(GenPassDeriveOpts),
+    #[command(
+        subcommand,
+        about = "Check passwords against a local Pwned Passwords dataset"
+    )]
+    Breached(BreachedSubCommand),
+}
+
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum BreachedSubCommand {
+    #[command(about = "Look passwords up in the dataset")]
+    Check(BreachedCheckOpts),
+    #[command(about = "Build a bloom filter index for the dataset")]
+    Index(BreachedIndexOpts),
+}
+
+#[derive(Parser, Debug)]
+pub struct GenPassDeriveOpts {
+    #[arg(long)]
+    pub site: String,
+
+    #[arg(long)]
+    pub login: String,
+
+    #[arg(long, default_value_t = 1)]
+    pub counter: u32,
+
+    #[arg(long, value_parser = parse_derive_kdf, default_value = "blake3")]
+    pub kdf: DeriveKdf,
+
+    #[arg(long, help = "Read the master secret from this environment variable")]
+    pub master_env: Option<String>,
+
+    #[arg(long, value_parser = verify_file, help = "Read the master secret from a key file")]
+    pub master_file: Option<String>,
+
+    #[arg(short, long, default_value_t = 16)]
+    pub length: u8,
+
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    pub upper: bool,
+
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    pub lower: bool,
+
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    pub number: bool,
+
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    pub symbol: bool,
+}
+
+impl CmdExecutor for GenPassDeriveOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let master = if let Some(path) = &self.master_file {
+            MasterSecret::load(path)?
+        } else if let Some(name) = &self.master_env {
+            MasterSecret::from_env(name)?
+        } else {
+            MasterSecret::prompt()?
+        };
+
+        let seed = process::derive_pass_seed(
+            master.secret(),
+            &self.site,
+            &self.login,
+            self.counter,
+            self.kdf,
+        )?;
+        let password = process::process_derive_pass(
+            &seed,
             self.length,
             self.upper,
             self.lower,
@@ -30,9 +150,103 @@ impl CmdExecutor for GenPassOpts {
             self.symbol,
         )?;
         println!("{}", password);
+        Ok(())
+    }
+}
 
-        let estimate = zxcvbn(&password, &[])?;
-        eprintln!("Estimated strength: {}", estimate.score());
+#[derive(Parser, Debug)]
+pub struct BreachedCheckOpts {
+    #[arg(short, long, value_parser = verify_file, help = "File with one password per line, prompted for when omitted")]
+    pub input: Option<String>,
+
+    #[arg(long, value_parser = verify_file, help = "Full SHA-1 Pwned Passwords file ordered by hash (k-anonymity range files are not supported)")]
+    pub dataset: Option<String>,
+
+    #[arg(long, value_parser = verify_file, help = "Bloom filter index built by `breached index`")]
+    pub index: Option<String>,
+}
+
+impl CmdExecutor for BreachedCheckOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let dataset = self.dataset.as_deref();
+        let index = self.index.as_deref();
+
+        match &self.input {
+            Some(input) => {
+                let mut content = String::new();
+                get_reader(input)?.read_to_string(&mut content)?;
+                // Report by line number so the passwords never reach stdout
+                for (number, password) in content.lines().enumerate() {
+                    if password.is_empty() {
+                        continue;
+                    }
+                    let status = process::process_breached_check(password, dataset, index)?;
+                    println!("line {}: {}", number + 1, status);
+                }
+            }
+            None => {
+                let password = rpassword::prompt_password("Password: ")?;
+                let status = process::process_breached_check(&password, dataset, index)?;
+                println!("{}", status);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct BreachedIndexOpts {
+    #[arg(long, value_parser = verify_file, help = "Full SHA-1 Pwned Passwords file ordered by hash (k-anonymity range files are not supported)")]
+    pub dataset: String,
+
+    #[arg(short, long)]
+    pub output: String,
+
+    #[arg(long, default_value_t = 0.001, help = "Target false positive rate")]
+    pub fp_rate: f64,
+}
+
+impl CmdExecutor for BreachedIndexOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let count = process::process_breached_index(&self.dataset, &self.output, self.fp_rate)?;
+        eprintln!("Indexed {} hashes into {}", count, self.output);
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub enum DeriveKdf {
+    Blake3,
+    Argon2id,
+}
+
+pub fn parse_derive_kdf(kdf: &str) -> Result<DeriveKdf, anyhow::Error> {
+    kdf.parse()
+}
+
+impl FromStr for DeriveKdf {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "blake3" => Ok(DeriveKdf::Blake3),
+            "argon2id" => Ok(DeriveKdf::Argon2id),
+            v => anyhow::bail!("Unsupported kdf: {}", v),
+        }
+    }
+}
+
+impl From<DeriveKdf> for &'static str {
+    fn from(kdf: DeriveKdf) -> Self {
+        match kdf {
+            DeriveKdf::Blake3 => "blake3",
+            DeriveKdf::Argon2id => "argon2id",
+        }
+    }
+}
+
+impl fmt::Display for DeriveKdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
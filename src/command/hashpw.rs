@@ -0,0 +1,142 @@
+use crate::{process, CmdExecutor};
+use clap::{Args, Parser};
+use enum_dispatch::enum_dispatch;
+use humantime::parse_duration;
+use std::{fmt, str::FromStr, time::Duration};
+
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum HashPwSubCommand {
+    #[command(about = "Hash a password into a PHC string")]
+    Hash(HashPwHashOpts),
+    #[command(about = "Verify a password against a PHC string")]
+    Verify(HashPwVerifyOpts),
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct HashCostOpts {
+    #[arg(long, help = "Argon2 memory cost in KiB")]
+    pub memory: Option<u32>,
+    #[arg(long, help = "Argon2 time cost or PBKDF2 rounds")]
+    pub iterations: Option<u32>,
+    #[arg(long, help = "Argon2/scrypt parallelism")]
+    pub parallelism: Option<u32>,
+    #[arg(long, help = "bcrypt cost")]
+    pub cost: Option<u32>,
+    #[arg(long, help = "scrypt log2(N)")]
+    pub log_n: Option<u8>,
+    #[arg(long, help = "scrypt block size (r)")]
+    pub block_size: Option<u32>,
+}
+
+#[derive(Parser, Debug)]
+pub struct HashPwHashOpts {
+    #[arg(short, long, value_parser = parse_hash_algorithm, default_value = "argon2id")]
+    pub algorithm: HashAlgorithm,
+    #[arg(short, long, help = "Password to hash, prompted for when omitted")]
+    pub password: Option<String>,
+    #[arg(long, value_parser = parse_duration, help = "Pick Argon2 costs that take this long, e.g. 500ms")]
+    pub calibrate: Option<Duration>,
+    #[command(flatten)]
+    pub cost: HashCostOpts,
+}
+
+impl CmdExecutor for HashPwHashOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut cost = self.cost;
+        if let Some(target) = self.calibrate {
+            if !matches!(self.algorithm, HashAlgorithm::Argon2id) {
+                anyhow::bail!("--calibrate is only supported for argon2id");
+            }
+            let params = process::process_hashpw_calibrate(target, &cost)?;
+            eprintln!(
+                "Calibrated argon2id: m={}, t={}, p={}",
+                params.m_cost(),
+                params.t_cost(),
+                params.p_cost()
+            );
+            cost.memory = Some(params.m_cost());
+            cost.iterations = Some(params.t_cost());
+            cost.parallelism = Some(params.p_cost());
+        }
+
+        let password = match self.password {
+            Some(password) => password,
+            None => {
+                let password = rpassword::prompt_password("Password: ")?;
+                let confirm = rpassword::prompt_password("Confirm password: ")?;
+                if password != confirm {
+                    anyhow::bail!("Passwords do not match");
+                }
+                password
+            }
+        };
+
+        let hash = process::process_hashpw_hash(&password, self.algorithm, &cost)?;
+        println!("{}", hash);
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct HashPwVerifyOpts {
+    #[arg(long, help = "PHC (or bcrypt) hash string")]
+    pub hash: String,
+    #[arg(short, long, help = "Password to verify, prompted for when omitted")]
+    pub password: Option<String>,
+}
+
+impl CmdExecutor for HashPwVerifyOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let password = match self.password {
+            Some(password) => password,
+            None => rpassword::prompt_password("Password: ")?,
+        };
+        let valid = process::process_hashpw_verify(&password, &self.hash)?;
+        println!("{}", valid);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum HashAlgorithm {
+    Argon2id,
+    Bcrypt,
+    Scrypt,
+    Pbkdf2,
+}
+
+pub fn parse_hash_algorithm(algorithm: &str) -> Result<HashAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "argon2id" => Ok(HashAlgorithm::Argon2id),
+            "bcrypt" => Ok(HashAlgorithm::Bcrypt),
+            "scrypt" => Ok(HashAlgorithm::Scrypt),
+            "pbkdf2" => Ok(HashAlgorithm::Pbkdf2),
+            v => anyhow::bail!("Unsupported algorithm: {}", v),
+        }
+    }
+}
+
+impl From<HashAlgorithm> for &'static str {
+    fn from(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Argon2id => "argon2id",
+            HashAlgorithm::Bcrypt => "bcrypt",
+            HashAlgorithm::Scrypt => "scrypt",
+            HashAlgorithm::Pbkdf2 => "pbkdf2",
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
@@ -0,0 +1,99 @@
+use super::verify_file;
+use crate::{
+    get_binary_writer, get_reader, get_writer, process, process::HexdumpConfig, CmdExecutor,
+};
+use clap::Parser;
+use std::{fmt, io::IsTerminal, str::FromStr};
+
+#[derive(Parser, Debug)]
+pub struct HexdumpOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(short, long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..=256), help = "Bytes per line")]
+    pub columns: u16,
+    #[arg(
+        short,
+        long,
+        default_value_t = 2,
+        help = "Bytes per group, 0 for no grouping"
+    )]
+    pub group: usize,
+    #[arg(short, long, default_value_t = 0, help = "Start at this byte offset")]
+    pub seek: u64,
+    #[arg(short, long, help = "Stop after this many bytes")]
+    pub length: Option<u64>,
+    #[arg(short, long, help = "Use uppercase hex digits")]
+    pub uppercase: bool,
+    #[arg(long, value_parser = parse_color_mode, default_value = "auto")]
+    pub color: ColorMode,
+    #[arg(short, long, help = "Rebuild the binary from a hex dump")]
+    pub reverse: bool,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
+}
+
+impl CmdExecutor for HexdumpOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut reader = get_reader(&self.input)?;
+        if self.reverse {
+            let mut writer = get_binary_writer(&self.output, self.force)?;
+            return process::process_hexdump_reverse(&mut reader, &mut writer);
+        }
+
+        let color = match self.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => self.output == "-" && std::io::stdout().is_terminal(),
+        };
+        let config = HexdumpConfig {
+            columns: self.columns as usize,
+            group: self.group,
+            uppercase: self.uppercase,
+            color,
+        };
+        let mut writer = get_writer(&self.output)?;
+        process::process_hexdump(&mut reader, &mut writer, config, self.seek, self.length)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+pub fn parse_color_mode(mode: &str) -> Result<ColorMode, anyhow::Error> {
+    mode.parse()
+}
+
+impl FromStr for ColorMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            v => anyhow::bail!("Unsupported color mode: {}", v),
+        }
+    }
+}
+
+impl From<ColorMode> for &'static str {
+    fn from(mode: ColorMode) -> Self {
+        match mode {
+            ColorMode::Auto => "auto",
+            ColorMode::Always => "always",
+            ColorMode::Never => "never",
+        }
+    }
+}
+
+impl fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
@@ -1,15 +1,25 @@
 mod base64;
+mod codec;
+mod compress;
 mod csv;
+mod gen;
 mod gen_pass;
+mod hashpw;
+mod hexdump;
 mod http;
 mod jwt;
+mod otp;
 mod text;
+mod url;
 
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 use std::path::{Path, PathBuf};
 
-pub use self::{base64::*, csv::*, gen_pass::*, http::*, jwt::*, text::*};
+pub use self::{
+    base64::*, codec::*, compress::*, csv::*, gen::*, gen_pass::*, hashpw::*, hexdump::*, http::*,
+    jwt::*, otp::*, text::*, url::*,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "rcli", version, author, about, long_about = None)]
@@ -33,6 +43,31 @@ pub enum SubCommand {
     Http(HttpSubCommand),
     #[command(subcommand, about = "JWT encode/decode")]
     Jwt(JwtSubCommand),
+    #[command(subcommand, about = "TOTP/HOTP one-time passwords")]
+    Otp(OtpSubCommand),
+    #[command(
+        name = "hashpw",
+        subcommand,
+        about = "Password hashing and verification"
+    )]
+    HashPw(HashPwSubCommand),
+    #[command(subcommand, about = "Generate identifiers and random tokens")]
+    Gen(GenSubCommand),
+    #[command(about = "Encode a file with base16/32/58/62/85/64 codecs")]
+    Encode(CodecEncodeOpts),
+    #[command(about = "Decode a file with base16/32/58/62/85/64 codecs")]
+    Decode(CodecDecodeOpts),
+    #[command(
+        subcommand,
+        about = "URL, form, HTML, quoted-printable and IDNA encode/decode"
+    )]
+    Url(UrlSubCommand),
+    #[command(about = "Hex dump a file in xxd format, or reverse a dump")]
+    Hexdump(HexdumpOpts),
+    #[command(about = "Compress a file with gzip, deflate, zstd, brotli or xz")]
+    Compress(CompressOpts),
+    #[command(about = "Decompress a file, detecting the algorithm from magic bytes")]
+    Decompress(DecompressOpts),
 }
 
 fn verify_file(filename: &str) -> Result<String, &'static str> {
@@ -0,0 +1,159 @@
+use crate::{process, process::OtpConfig, CmdExecutor};
+use clap::{Args, Parser};
+use enum_dispatch::enum_dispatch;
+use std::{fmt, str::FromStr};
+
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum OtpSubCommand {
+    #[command(about = "Generate a new OTP secret and otpauth URI")]
+    Generate(OtpGenerateOpts),
+    #[command(about = "Show the current TOTP, or the HOTP for a counter")]
+    Code(OtpCodeOpts),
+    #[command(about = "Verify a one-time password")]
+    Verify(OtpVerifyOpts),
+}
+
+#[derive(Args, Debug)]
+pub struct OtpParams {
+    #[arg(long, value_parser = parse_otp_algorithm, default_value = "sha1")]
+    pub algorithm: OtpAlgorithm,
+    #[arg(long, value_parser = clap::value_parser!(u32).range(6..=8), default_value_t = 6)]
+    pub digits: u32,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = 30)]
+    pub period: u64,
+}
+
+impl From<OtpParams> for OtpConfig {
+    fn from(params: OtpParams) -> Self {
+        Self {
+            algorithm: params.algorithm,
+            digits: params.digits,
+            period: params.period,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct OtpGenerateOpts {
+    #[arg(long, default_value = "rcli")]
+    pub issuer: String,
+    #[arg(long)]
+    pub account: String,
+    #[arg(long, help = "Generate an HOTP URI starting at this counter")]
+    pub counter: Option<u64>,
+    #[command(flatten)]
+    pub params: OtpParams,
+}
+
+impl CmdExecutor for OtpGenerateOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let (secret, uri) = process::process_otp_generate(
+            &self.issuer,
+            &self.account,
+            self.params.into(),
+            self.counter,
+        )?;
+        println!("{}", secret);
+        println!("{}", uri);
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct OtpCodeOpts {
+    #[arg(short, long, help = "Base32 encoded secret")]
+    pub secret: String,
+    #[arg(long, help = "Produce an HOTP for this counter instead of a TOTP")]
+    pub counter: Option<u64>,
+    #[command(flatten)]
+    pub params: OtpParams,
+}
+
+impl CmdExecutor for OtpCodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let code = process::process_otp_code(&self.secret, self.params.into(), self.counter)?;
+        println!("{}", code);
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct OtpVerifyOpts {
+    #[arg(short, long, help = "Base32 encoded secret")]
+    pub secret: String,
+    #[arg(short, long)]
+    pub code: String,
+    #[arg(long, help = "Verify as HOTP starting at this counter")]
+    pub counter: Option<u64>,
+    #[arg(
+        short,
+        long,
+        default_value_t = 1,
+        help = "Number of steps of drift to accept, at most 100"
+    )]
+    pub window: u64,
+    #[command(flatten)]
+    pub params: OtpParams,
+}
+
+impl CmdExecutor for OtpVerifyOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let matched = process::process_otp_verify(
+            &self.secret,
+            &self.code,
+            self.params.into(),
+            self.counter,
+            self.window,
+        )?;
+        println!("{}", matched.is_some());
+        if let (Some(_), Some(matched)) = (self.counter, matched) {
+            eprintln!(
+                "Matched counter {}, continue from {}",
+                matched,
+                matched.saturating_add(1)
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+pub fn parse_otp_algorithm(algorithm: &str) -> Result<OtpAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl FromStr for OtpAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "sha1" => Ok(OtpAlgorithm::Sha1),
+            "sha256" => Ok(OtpAlgorithm::Sha256),
+            "sha512" => Ok(OtpAlgorithm::Sha512),
+            v => anyhow::bail!("Unsupported algorithm: {}", v),
+        }
+    }
+}
+
+impl From<OtpAlgorithm> for &'static str {
+    fn from(algorithm: OtpAlgorithm) -> Self {
+        match algorithm {
+            OtpAlgorithm::Sha1 => "sha1",
+            OtpAlgorithm::Sha256 => "sha256",
+            OtpAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl fmt::Display for OtpAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
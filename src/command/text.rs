@@ -1,8 +1,13 @@
 use super::{verify_file, verify_path};
-use crate::{process, CmdExecutor};
-use clap::Parser;
+use crate::{get_binary_writer, get_reader, get_writer, process, CmdExecutor};
+use clap::{ArgGroup, Args, Parser};
 use enum_dispatch::enum_dispatch;
-use std::{fmt, path::PathBuf, str::FromStr};
+use std::{
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use tokio::fs;
 
 #[derive(Parser, Debug)]
@@ -18,6 +23,23 @@ pub enum TextSubCommand {
     Encrypt(EncryptOpts),
     #[command(about = "Decrypt a message")]
     Decrypt(DecryptOpts),
+    #[command(about = "Hash files or directories, or verify a checksum file")]
+    Hash(TextHashOpts),
+    #[command(about = "Write a signed BLAKE3 manifest of a directory")]
+    SignDir(TextSignDirOpts),
+    #[command(about = "Check a directory against its signed manifest")]
+    VerifyDir(TextVerifyDirOpts),
+    #[command(about = "Derive a subkey file from a master key and a context string")]
+    Derive(TextDeriveOpts),
+    #[command(subcommand, about = "Convert keys between formats")]
+    Key(KeySubCommand),
+}
+
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum KeySubCommand {
+    #[command(about = "Convert a key to raw, hex, PKCS#8, SPKI, OpenSSH or JWK")]
+    Convert(KeyConvertOpts),
 }
 
 #[derive(Parser, Debug)]
@@ -28,12 +50,17 @@ pub struct TextSignOpts {
     pub key: String,
     #[arg(long, value_parser = parse_sign_format, default_value = "blake3")]
     pub format: TextSignFormat,
+    #[arg(short, long, help = "Write the signature to this file, e.g. file.sig")]
+    pub output: Option<PathBuf>,
 }
 
 impl CmdExecutor for TextSignOpts {
     async fn execute(self) -> anyhow::Result<()> {
         let signed = process::process_text_sign(&self.input, &self.key, self.format)?;
-        println!("{}", signed);
+        match self.output {
+            Some(output) => fs::write(output, format!("{}\n", signed)).await?,
+            None => println!("{}", signed),
+        }
         Ok(())
     }
 }
@@ -46,13 +73,20 @@ pub struct TextVerifyOpts {
     pub key: String,
     #[arg(long, value_parser = parse_sign_format, default_value = "blake3")]
     pub format: TextSignFormat,
-    #[arg(short, long)]
-    pub sig: String,
+    #[arg(short, long, required_unless_present = "sig_file")]
+    pub sig: Option<String>,
+    #[arg(long, value_parser = verify_file, conflicts_with = "sig", help = "Read the signature from this file")]
+    pub sig_file: Option<String>,
 }
 
 impl CmdExecutor for TextVerifyOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let valid = process::process_text_verify(&self.input, &self.key, &self.sig, self.format)?;
+        let sig = match (self.sig, self.sig_file) {
+            (Some(sig), _) => sig,
+            (None, Some(path)) => fs::read_to_string(path).await?.trim().to_string(),
+            (None, None) => unreachable!("clap requires --sig or --sig-file"),
+        };
+        let valid = process::process_text_verify(&self.input, &self.key, &sig, self.format)?;
         println!("{}", valid);
         Ok(())
     }
@@ -70,18 +104,15 @@ impl CmdExecutor for GenerateOpts {
     async fn execute(self) -> anyhow::Result<()> {
         let keys = process::process_text_generate(self.format)?;
         match self.format {
-            TextSignFormat::Blake3 => {
-                let name = self.output.join("blake3.key");
+            // shared secrets are a single key file
+            format if keys.len() == 1 => {
+                let name = self.output.join(format!("{}.key", format));
                 fs::write(name, &keys[0]).await?;
             }
-            TextSignFormat::Ed25519 => {
-                let name = self.output;
-                fs::write(name.join("ed25519.sk"), &keys[0]).await?;
-                fs::write(name.join("ed25519.pk"), &keys[1]).await?;
-            }
-            TextSignFormat::Chacha => {
+            format => {
                 let name = self.output;
-                fs::write(name.join("chacha.key"), &keys[0]).await?;
+                fs::write(name.join(format!("{}.sk", format)), &keys[0]).await?;
+                fs::write(name.join(format!("{}.pk", format)), &keys[1]).await?;
             }
         }
         Ok(())
@@ -92,7 +123,18 @@ impl CmdExecutor for GenerateOpts {
 pub enum TextSignFormat {
     Blake3,
     Ed25519,
+    Ed25519ph,
     Chacha,
+    HmacSha256,
+    HmacSha384,
+    HmacSha512,
+    HmacSha3_256,
+    P256,
+    P384,
+    Secp256k1,
+    RsaPss,
+    Ed448,
+    X25519,
 }
 
 pub fn parse_sign_format(format: &str) -> Result<TextSignFormat, anyhow::Error> {
@@ -106,7 +148,18 @@ impl FromStr for TextSignFormat {
         match value {
             "blake3" => Ok(TextSignFormat::Blake3),
             "ed25519" => Ok(TextSignFormat::Ed25519),
+            "ed25519ph" => Ok(TextSignFormat::Ed25519ph),
             "chacha" => Ok(TextSignFormat::Chacha),
+            "hmac-sha256" => Ok(TextSignFormat::HmacSha256),
+            "hmac-sha384" => Ok(TextSignFormat::HmacSha384),
+            "hmac-sha512" => Ok(TextSignFormat::HmacSha512),
+            "hmac-sha3-256" => Ok(TextSignFormat::HmacSha3_256),
+            "p256" => Ok(TextSignFormat::P256),
+            "p384" => Ok(TextSignFormat::P384),
+            "secp256k1" => Ok(TextSignFormat::Secp256k1),
+            "rsa-pss" => Ok(TextSignFormat::RsaPss),
+            "ed448" => Ok(TextSignFormat::Ed448),
+            "x25519" | "age" => Ok(TextSignFormat::X25519),
             v => anyhow::bail!("Unsupported format: {}", v),
         }
     }
@@ -117,7 +170,18 @@ impl From<TextSignFormat> for &'static str {
         match format {
             TextSignFormat::Blake3 => "blake3",
             TextSignFormat::Ed25519 => "ed25519",
+            TextSignFormat::Ed25519ph => "ed25519ph",
             TextSignFormat::Chacha => "chacha",
+            TextSignFormat::HmacSha256 => "hmac-sha256",
+            TextSignFormat::HmacSha384 => "hmac-sha384",
+            TextSignFormat::HmacSha512 => "hmac-sha512",
+            TextSignFormat::HmacSha3_256 => "hmac-sha3-256",
+            TextSignFormat::P256 => "p256",
+            TextSignFormat::P384 => "p384",
+            TextSignFormat::Secp256k1 => "secp256k1",
+            TextSignFormat::RsaPss => "rsa-pss",
+            TextSignFormat::Ed448 => "ed448",
+            TextSignFormat::X25519 => "x25519",
         }
     }
 }
@@ -128,34 +192,607 @@ impl fmt::Display for TextSignFormat {
     }
 }
 
+#[derive(Args, Debug)]
+pub struct EncryptKeyOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub key: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with = "key",
+        help = "Prompt for a passphrase instead of using a key file"
+    )]
+    pub passphrase: bool,
+    #[arg(long, conflicts_with_all = ["key", "passphrase"], help = "Read the passphrase from this environment variable")]
+    pub passphrase_env: Option<String>,
+}
+
+impl EncryptKeyOpts {
+    fn resolve(self, confirm: bool) -> anyhow::Result<process::EncryptionKey> {
+        if let Some(key) = self.key {
+            return Ok(process::EncryptionKey::File(key));
+        }
+        let passphrase = match self.passphrase_env {
+            Some(name) => std::env::var(&name)
+                .map_err(|_| anyhow::anyhow!("Environment variable {} is not set", name))?,
+            None => {
+                let passphrase = rpassword::prompt_password("Passphrase: ")?;
+                if confirm && passphrase != rpassword::prompt_password("Confirm passphrase: ")? {
+                    anyhow::bail!("Passphrases do not match");
+                }
+                passphrase
+            }
+        };
+        Ok(process::EncryptionKey::Passphrase(passphrase))
+    }
+}
+
 #[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("key_source").required(true).args(["key", "passphrase", "passphrase_env", "recipient"])))]
 pub struct EncryptOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
-    #[arg(short, long, value_parser = verify_file)]
-    pub key: String,
+    #[command(flatten)]
+    pub key: EncryptKeyOpts,
+    #[arg(
+        short,
+        long,
+        help = "Encrypt to this age recipient (age1...), may be repeated"
+    )]
+    pub recipient: Vec<String>,
+    #[arg(
+        long,
+        conflicts_with = "key",
+        help = "Write the age format, implied by --recipient"
+    )]
+    pub age: bool,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(long, value_parser = parse_aead_cipher, default_value = "chacha20", conflicts_with_all = ["age", "recipient"])]
+    pub cipher: AeadCipher,
+    #[arg(
+        long,
+        default_value = "",
+        conflicts_with_all = ["age", "recipient"],
+        help = "Associated data that must match on decrypt"
+    )]
+    pub aad: String,
+    #[arg(
+        long,
+        help = "Write text instead of raw binary, base64url or PEM for age"
+    )]
+    pub armor: bool,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
 }
 
 impl CmdExecutor for EncryptOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let encrypted = process::process_text_encrypt(&self.input, &self.key)?;
-        println!("{}", encrypted);
+        // resolve the key first, a failed passphrase prompt must not truncate the output
+        let age = !self.recipient.is_empty() || self.age;
+        let key = match self.recipient {
+            recipients if !recipients.is_empty() => process::EncryptionKey::Recipients(recipients),
+            _ => self.key.resolve(true)?,
+        };
+        let mut reader = get_reader(&self.input)?;
+        let mut writer = if self.armor {
+            get_writer(&self.output)?
+        } else {
+            get_binary_writer(&self.output, self.force)?
+        };
+        if age {
+            return process::process_age_encrypt(&mut reader, &mut writer, &key, self.armor);
+        }
+        process::process_text_encrypt(
+            &mut reader,
+            &mut writer,
+            &key,
+            self.cipher,
+            self.aad.as_bytes(),
+            self.armor,
+        )?;
         Ok(())
     }
 }
 
 #[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("key_source").required(true).args(["key", "passphrase", "passphrase_env", "identity"])))]
 pub struct DecryptOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
-    #[arg(short, long, value_parser = verify_file)]
-    pub key: String,
+    #[command(flatten)]
+    pub key: EncryptKeyOpts,
+    #[arg(
+        long,
+        value_parser = verify_file,
+        help = "age identity file to decrypt with, may be repeated"
+    )]
+    pub identity: Vec<String>,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(
+        long,
+        default_value = "",
+        help = "Associated data given when encrypting"
+    )]
+    pub aad: String,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
 }
 
 impl CmdExecutor for DecryptOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let decrypted = process::process_text_decrypt(&self.input, &self.key)?;
-        println!("{}", decrypted);
+        let key = match self.identity {
+            identities if !identities.is_empty() => process::EncryptionKey::Identities(identities),
+            _ => self.key.resolve(false)?,
+        };
+        let mut reader = get_reader(&self.input)?;
+        let mut writer = get_binary_writer(&self.output, self.force)?;
+        process::process_text_decrypt(&mut reader, &mut writer, &key, self.aad.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AeadCipher {
+    ChaCha20,
+    XChaCha20,
+    Aes256Gcm,
+    Aes256GcmSiv,
+}
+
+impl AeadCipher {
+    /// Identifier stored in the encrypted stream header
+    pub fn id(&self) -> u8 {
+        match self {
+            AeadCipher::ChaCha20 => 1,
+            AeadCipher::XChaCha20 => 2,
+            AeadCipher::Aes256Gcm => 3,
+            AeadCipher::Aes256GcmSiv => 4,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(AeadCipher::ChaCha20),
+            2 => Some(AeadCipher::XChaCha20),
+            3 => Some(AeadCipher::Aes256Gcm),
+            4 => Some(AeadCipher::Aes256GcmSiv),
+            _ => None,
+        }
+    }
+
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            AeadCipher::XChaCha20 => 24,
+            _ => 12,
+        }
+    }
+}
+
+pub fn parse_aead_cipher(cipher: &str) -> Result<AeadCipher, anyhow::Error> {
+    cipher.parse()
+}
+
+impl FromStr for AeadCipher {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "chacha20" | "chacha20-poly1305" => Ok(AeadCipher::ChaCha20),
+            "xchacha20" | "xchacha20-poly1305" => Ok(AeadCipher::XChaCha20),
+            "aes256gcm" | "aes-256-gcm" => Ok(AeadCipher::Aes256Gcm),
+            "aes256gcmsiv" | "aes-256-gcm-siv" => Ok(AeadCipher::Aes256GcmSiv),
+            v => anyhow::bail!("Unsupported cipher: {}", v),
+        }
+    }
+}
+
+impl From<AeadCipher> for &'static str {
+    fn from(cipher: AeadCipher) -> Self {
+        match cipher {
+            AeadCipher::ChaCha20 => "chacha20",
+            AeadCipher::XChaCha20 => "xchacha20",
+            AeadCipher::Aes256Gcm => "aes256gcm",
+            AeadCipher::Aes256GcmSiv => "aes256gcmsiv",
+        }
+    }
+}
+
+impl fmt::Display for AeadCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct DeriveCostOpts {
+    #[arg(long, help = "Argon2 memory cost in KiB")]
+    pub memory: Option<u32>,
+    #[arg(long, help = "Argon2 time cost")]
+    pub iterations: Option<u32>,
+    #[arg(long, help = "Argon2 parallelism")]
+    pub parallelism: Option<u32>,
+}
+
+#[derive(Parser, Debug)]
+pub struct TextDeriveOpts {
+    #[arg(short, long, value_parser = verify_file, help = "Master key file")]
+    pub key: String,
+    #[arg(long, value_parser = parse_kdf_algorithm, default_value = "hkdf-sha256")]
+    pub kdf: KdfAlgorithm,
+    #[arg(
+        short,
+        long,
+        alias = "info",
+        help = "Context string, e.g. \"payments 2024-05 api key\""
+    )]
+    pub context: String,
+    #[arg(
+        long,
+        default_value = "",
+        help = "Salt for HKDF (optional) or Argon2id (required)"
+    )]
+    pub salt: String,
+    #[arg(long, value_parser = parse_sign_format, default_value = "blake3", help = "Kind of key file to write")]
+    pub format: TextSignFormat,
+    #[arg(long, help = "Key length in bytes, only for hmac-* keys")]
+    pub length: Option<usize>,
+    #[command(flatten)]
+    pub cost: DeriveCostOpts,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "Key file to write, key pairs also write the .pk next to it"
+    )]
+    pub output: String,
+}
+
+impl CmdExecutor for TextDeriveOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let keys = process::process_text_derive(
+            &self.key,
+            self.kdf,
+            self.format,
+            &self.context,
+            self.salt.as_bytes(),
+            self.length,
+            &self.cost,
+        )?;
+        match keys.as_slice() {
+            [key] => get_writer(&self.output)?.write_all(key)?,
+            [sk, pk] => {
+                if self.output == "-" {
+                    anyhow::bail!("Key pairs need --output");
+                }
+                let output = Path::new(&self.output);
+                fs::write(output, sk).await?;
+                fs::write(output.with_extension("pk"), pk).await?;
+            }
+            _ => unreachable!("key files are a single key or a key pair"),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum KdfAlgorithm {
+    HkdfSha256,
+    HkdfSha512,
+    Blake3,
+    Argon2id,
+}
+
+pub fn parse_kdf_algorithm(kdf: &str) -> Result<KdfAlgorithm, anyhow::Error> {
+    kdf.parse()
+}
+
+impl FromStr for KdfAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "hkdf-sha256" | "hkdf" => Ok(KdfAlgorithm::HkdfSha256),
+            "hkdf-sha512" => Ok(KdfAlgorithm::HkdfSha512),
+            "blake3" => Ok(KdfAlgorithm::Blake3),
+            "argon2id" | "argon2" => Ok(KdfAlgorithm::Argon2id),
+            v => anyhow::bail!("Unsupported kdf: {}", v),
+        }
+    }
+}
+
+impl From<KdfAlgorithm> for &'static str {
+    fn from(kdf: KdfAlgorithm) -> Self {
+        match kdf {
+            KdfAlgorithm::HkdfSha256 => "hkdf-sha256",
+            KdfAlgorithm::HkdfSha512 => "hkdf-sha512",
+            KdfAlgorithm::Blake3 => "blake3",
+            KdfAlgorithm::Argon2id => "argon2id",
+        }
+    }
+}
+
+impl fmt::Display for KdfAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct KeyConvertOpts {
+    #[arg(short, long, value_parser = verify_file, help = "Key in any supported format")]
+    pub input: String,
+    #[arg(long, value_parser = parse_sign_format, default_value = "ed25519")]
+    pub format: TextSignFormat,
+    #[arg(long, value_parser = parse_key_format)]
+    pub to: KeyFormat,
+    #[arg(long, help = "Write the public key of a secret key")]
+    pub public: bool,
+    #[arg(
+        long,
+        help = "Read a bare raw, hex or base64 input as a public key, implied for *.pk"
+    )]
+    pub raw_public: bool,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(long, help = "Write binary output to the terminal anyway")]
+    pub force: bool,
+}
+
+impl CmdExecutor for KeyConvertOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        if !matches!(
+            self.format,
+            TextSignFormat::Ed25519 | TextSignFormat::Ed25519ph
+        ) {
+            anyhow::bail!(
+                "Key conversion is not supported for {} keys yet",
+                self.format
+            );
+        }
+        // generate names public keys *.pk
+        let raw_public = self.raw_public
+            || Path::new(&self.input)
+                .extension()
+                .is_some_and(|ext| ext == "pk");
+        let key = process::process_key_convert(&self.input, self.to, self.public, raw_public)?;
+        let mut writer = match self.to {
+            KeyFormat::Raw | KeyFormat::Pkcs8Der | KeyFormat::SpkiDer => {
+                get_binary_writer(&self.output, self.force)?
+            }
+            _ => get_writer(&self.output)?,
+        };
+        writer.write_all(&key)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum KeyFormat {
+    Raw,
+    Hex,
+    Rcli,
+    Pkcs8Der,
+    Pkcs8Pem,
+    SpkiDer,
+    SpkiPem,
+    OpenSsh,
+    Jwk,
+}
+
+pub fn parse_key_format(format: &str) -> Result<KeyFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for KeyFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "raw" => Ok(KeyFormat::Raw),
+            "hex" => Ok(KeyFormat::Hex),
+            "rcli" => Ok(KeyFormat::Rcli),
+            "pkcs8" | "pkcs8-der" => Ok(KeyFormat::Pkcs8Der),
+            "pkcs8-pem" | "pem" => Ok(KeyFormat::Pkcs8Pem),
+            "spki" | "spki-der" => Ok(KeyFormat::SpkiDer),
+            "spki-pem" => Ok(KeyFormat::SpkiPem),
+            "openssh" | "ssh" => Ok(KeyFormat::OpenSsh),
+            "jwk" => Ok(KeyFormat::Jwk),
+            v => anyhow::bail!("Unsupported key format: {}", v),
+        }
+    }
+}
+
+impl From<KeyFormat> for &'static str {
+    fn from(format: KeyFormat) -> Self {
+        match format {
+            KeyFormat::Raw => "raw",
+            KeyFormat::Hex => "hex",
+            KeyFormat::Rcli => "rcli",
+            KeyFormat::Pkcs8Der => "pkcs8-der",
+            KeyFormat::Pkcs8Pem => "pkcs8-pem",
+            KeyFormat::SpkiDer => "spki-der",
+            KeyFormat::SpkiPem => "spki-pem",
+            KeyFormat::OpenSsh => "openssh",
+            KeyFormat::Jwk => "jwk",
+        }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct TextSignDirOpts {
+    #[arg(value_parser = verify_path)]
+    pub dir: PathBuf,
+    #[arg(short, long, value_parser = verify_file, help = "Ed25519 private key")]
+    pub key: String,
+    #[arg(short, long, help = "Manifest path, defaults to DIR/MANIFEST.b3")]
+    pub manifest: Option<PathBuf>,
+}
+
+impl CmdExecutor for TextSignDirOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let (manifest, sig) = manifest_paths(&self.dir, self.manifest, None);
+        let count = process::process_text_sign_dir(&self.dir, &self.key, &manifest, &sig)?;
+        eprintln!(
+            "Signed {} files: {} {}",
+            count,
+            manifest.display(),
+            sig.display()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct TextVerifyDirOpts {
+    #[arg(value_parser = verify_path)]
+    pub dir: PathBuf,
+    #[arg(short, long, value_parser = verify_file, help = "Ed25519 public key")]
+    pub key: String,
+    #[arg(short, long, help = "Manifest path, defaults to DIR/MANIFEST.b3")]
+    pub manifest: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Manifest signature, defaults to the manifest path plus .sig"
+    )]
+    pub sig_file: Option<PathBuf>,
+}
+
+impl CmdExecutor for TextVerifyDirOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let (manifest, sig) = manifest_paths(&self.dir, self.manifest, self.sig_file);
+        let results = process::process_text_verify_dir(&self.dir, &self.key, &manifest, &sig)?;
+        let mismatched = results
+            .iter()
+            .filter(|(_, status)| *status != process::ManifestStatus::Ok)
+            .count();
+        for (path, status) in results {
+            println!("{}: {}", path, status);
+        }
+        if mismatched > 0 {
+            anyhow::bail!("{} file(s) do not match the signed manifest", mismatched);
+        }
+        Ok(())
+    }
+}
+
+fn manifest_paths(
+    dir: &Path,
+    manifest: Option<PathBuf>,
+    sig: Option<PathBuf>,
+) -> (PathBuf, PathBuf) {
+    let manifest = manifest.unwrap_or_else(|| dir.join("MANIFEST.b3"));
+    let sig = sig.unwrap_or_else(|| {
+        let mut sig = manifest.clone().into_os_string();
+        sig.push(".sig");
+        sig.into()
+    });
+    (manifest, sig)
+}
+
+#[derive(Parser, Debug)]
+pub struct TextHashOpts {
+    #[arg(
+        default_value = "-",
+        help = "Files or directories to hash, - for stdin"
+    )]
+    pub paths: Vec<String>,
+    #[arg(short, long, value_parser = parse_digest_algorithm, default_value = "sha256")]
+    pub algorithm: DigestAlgorithm,
+    #[arg(short, long, value_parser = verify_file, help = "Verify the checksums listed in this file")]
+    pub check: Option<String>,
+    #[arg(long, conflicts_with = "check", help = "Print a JSON report")]
+    pub json: bool,
+}
+
+impl CmdExecutor for TextHashOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        if let Some(check) = self.check {
+            let results = process::process_text_hash_check(&check, self.algorithm)?;
+            let failed = results
+                .iter()
+                .filter(|(_, status)| *status != process::CheckStatus::Ok)
+                .count();
+            for (path, status) in results {
+                println!("{}: {}", path, status);
+            }
+            if failed > 0 {
+                anyhow::bail!("{} computed checksum(s) did NOT match", failed);
+            }
+            return Ok(());
+        }
+
+        let report = process::process_text_hash(&self.paths, self.algorithm)?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            for file in report.files {
+                println!("{}", file);
+            }
+        }
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub enum DigestAlgorithm {
+    Sha256,
+    Sha512,
+    Sha3_256,
+    Sha3_512,
+    Blake2b,
+    Blake3,
+    Xxh3,
+    Crc32,
+}
+
+pub fn parse_digest_algorithm(algorithm: &str) -> Result<DigestAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "sha256" => Ok(DigestAlgorithm::Sha256),
+            "sha512" => Ok(DigestAlgorithm::Sha512),
+            "sha3-256" => Ok(DigestAlgorithm::Sha3_256),
+            "sha3-512" => Ok(DigestAlgorithm::Sha3_512),
+            "blake2b" => Ok(DigestAlgorithm::Blake2b),
+            "blake3" => Ok(DigestAlgorithm::Blake3),
+            "xxh3" => Ok(DigestAlgorithm::Xxh3),
+            "crc32" => Ok(DigestAlgorithm::Crc32),
+            v => anyhow::bail!("Unsupported algorithm: {}", v),
+        }
+    }
+}
+
+impl From<DigestAlgorithm> for &'static str {
+    fn from(algorithm: DigestAlgorithm) -> Self {
+        match algorithm {
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Sha512 => "sha512",
+            DigestAlgorithm::Sha3_256 => "sha3-256",
+            DigestAlgorithm::Sha3_512 => "sha3-512",
+            DigestAlgorithm::Blake2b => "blake2b",
+            DigestAlgorithm::Blake3 => "blake3",
+            DigestAlgorithm::Xxh3 => "xxh3",
+            DigestAlgorithm::Crc32 => "crc32",
+        }
+    }
+}
+
+impl fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
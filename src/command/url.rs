@@ -0,0 +1,134 @@
+use super::verify_file;
+use crate::{get_reader, get_writer, process, CmdExecutor};
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::{fmt, io::Read, str::FromStr};
+
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum UrlSubCommand {
+    #[command(
+        name = "encode",
+        about = "Percent, form, HTML, quoted-printable or IDNA encode"
+    )]
+    Encode(UrlEncodeOpts),
+    #[command(
+        name = "decode",
+        about = "Percent, form, HTML, quoted-printable or IDNA decode"
+    )]
+    Decode(UrlDecodeOpts),
+}
+
+#[derive(Parser, Debug)]
+pub struct UrlEncodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(long, value_parser = parse_url_format, default_value = "component")]
+    pub format: UrlFormat,
+}
+
+impl CmdExecutor for UrlEncodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let input = read_input(&self.input, self.format)?;
+        let encoded = process::process_url_encode(&input, self.format)?;
+        let mut writer = get_writer(&self.output)?;
+        write_output(&mut writer, encoded.as_bytes(), self.format)
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct UrlDecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(long, value_parser = parse_url_format, default_value = "component")]
+    pub format: UrlFormat,
+}
+
+impl CmdExecutor for UrlDecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let input = String::from_utf8(read_input(&self.input, self.format)?)?;
+        let decoded = process::process_url_decode(&input, self.format)?;
+        let mut writer = get_writer(&self.output)?;
+        write_output(&mut writer, &decoded, self.format)
+    }
+}
+
+/// Read the whole input, dropping the trailing newline of line-oriented formats
+fn read_input(input: &str, format: UrlFormat) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    get_reader(input)?.read_to_end(&mut buf)?;
+    if !matches!(format, UrlFormat::QuotedPrintable) {
+        let len = buf.trim_ascii_end().len();
+        buf.truncate(len);
+    }
+    Ok(buf)
+}
+
+fn write_output(
+    writer: &mut dyn std::io::Write,
+    data: &[u8],
+    format: UrlFormat,
+) -> anyhow::Result<()> {
+    writer.write_all(data)?;
+    if !matches!(format, UrlFormat::QuotedPrintable) {
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum UrlFormat {
+    Component,
+    Path,
+    Query,
+    Form,
+    Html,
+    QuotedPrintable,
+    Idna,
+}
+
+pub fn parse_url_format(format: &str) -> Result<UrlFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for UrlFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "component" => Ok(UrlFormat::Component),
+            "path" => Ok(UrlFormat::Path),
+            "query" => Ok(UrlFormat::Query),
+            "form" => Ok(UrlFormat::Form),
+            "html" => Ok(UrlFormat::Html),
+            "qp" | "quoted-printable" => Ok(UrlFormat::QuotedPrintable),
+            "idna" | "punycode" => Ok(UrlFormat::Idna),
+            v => anyhow::bail!("Unsupported format: {}", v),
+        }
+    }
+}
+
+impl From<UrlFormat> for &'static str {
+    fn from(format: UrlFormat) -> Self {
+        match format {
+            UrlFormat::Component => "component",
+            UrlFormat::Path => "path",
+            UrlFormat::Query => "query",
+            UrlFormat::Form => "form",
+            UrlFormat::Html => "html",
+            UrlFormat::QuotedPrintable => "qp",
+            UrlFormat::Idna => "idna",
+        }
+    }
+}
+
+impl fmt::Display for UrlFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
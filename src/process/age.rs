@@ -0,0 +1,579 @@
+use crate::process::{
+    nonce_prefix_len, open_chunks, process_pem_decode, process_pem_encode, seal_chunks,
+    EncryptionKey, KeyGenerator, TextDecrypt, TextEncrypt,
+};
+use anyhow::{anyhow, Result};
+use base64::prelude::*;
+use bech32::{FromBase32, ToBase32, Variant};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng},
+    ChaCha20Poly1305,
+};
+use chrono::{SecondsFormat, Utc};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::{
+    fmt, fs,
+    io::{BufRead, BufReader, Read, Write},
+    str::FromStr,
+};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+/// First line of every age v1 file
+pub const AGE_MAGIC: &str = "age-encryption.org/v1";
+pub const AGE_ARMOR_LABEL: &str = "AGE ENCRYPTED FILE";
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+const FILE_KEY_LEN: usize = 16;
+const PAYLOAD_NONCE_LEN: usize = 16;
+const SCRYPT_SALT_LEN: usize = 16;
+/// Same default work factor as the age tool, about a second and 256 MiB
+const SCRYPT_LOG_N: u8 = 18;
+/// Refuse headers asking for more than 4 GiB of scrypt memory
+const MAX_SCRYPT_LOG_N: u8 = 22;
+/// Stanza bodies are wrapped at 64 base64 columns, a shorter line ends the body
+const STANZA_COLUMNS: usize = 64;
+const MAX_HEADER_LINE: u64 = 4096;
+
+/// An age X25519 recipient, `age1...`
+#[derive(Debug, Clone)]
+pub struct AgeRecipient(PublicKey);
+
+/// An age X25519 identity, `AGE-SECRET-KEY-1...`
+#[derive(Clone)]
+pub struct AgeIdentity(StaticSecret);
+
+/// One `-> type args...` entry of the header, wrapping the file key for one recipient
+struct Stanza {
+    tag: String,
+    args: Vec<String>,
+    body: Vec<u8>,
+}
+
+pub enum AgeEncryptor {
+    Recipients(Vec<AgeRecipient>),
+    Passphrase { passphrase: String, log_n: u8 },
+}
+
+pub enum AgeDecryptor {
+    Identities(Vec<AgeIdentity>),
+    Passphrase(String),
+}
+
+impl FromStr for AgeRecipient {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let key = decode_bech32(value, RECIPIENT_HRP)
+            .map_err(|e| anyhow!("Invalid age recipient {}: {}", value, e))?;
+        Ok(Self(PublicKey::from(key)))
+    }
+}
+
+impl fmt::Display for AgeRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoded = bech32::encode(
+            RECIPIENT_HRP,
+            self.0.as_bytes().to_base32(),
+            Variant::Bech32,
+        )
+        .map_err(|_| fmt::Error)?;
+        write!(f, "{}", encoded)
+    }
+}
+
+impl AgeRecipient {
+    fn wrap(&self, file_key: &[u8]) -> Result<Stanza> {
+        let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+        let share = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&self.0);
+        if !shared.was_contributory() {
+            anyhow::bail!("Invalid age recipient {}", self);
+        }
+        let salt = [share.as_bytes().as_slice(), self.0.as_bytes()].concat();
+        let wrap_key = hkdf_sha256(&salt, shared.as_bytes(), X25519_LABEL);
+        Ok(Stanza {
+            tag: "X25519".to_string(),
+            args: vec![BASE64_STANDARD_NO_PAD.encode(share)],
+            body: seal_file_key(&wrap_key, file_key)?,
+        })
+    }
+}
+
+impl FromStr for AgeIdentity {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let key = decode_bech32(value, IDENTITY_HRP)
+            .map_err(|e| anyhow!("Invalid age identity: {}", e))?;
+        Ok(Self(StaticSecret::from(key)))
+    }
+}
+
+impl fmt::Display for AgeIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoded = bech32::encode(IDENTITY_HRP, self.0.as_bytes().to_base32(), Variant::Bech32)
+            .map_err(|_| fmt::Error)?;
+        write!(f, "{}", encoded.to_uppercase())
+    }
+}
+
+impl AgeIdentity {
+    pub fn to_public(&self) -> AgeRecipient {
+        AgeRecipient(PublicKey::from(&self.0))
+    }
+
+    /// Read every identity of an age identity file, skipping comments and blank lines
+    pub fn load_all(path: &str) -> Result<Vec<Self>> {
+        let content = fs::read_to_string(path)?;
+        let identities = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::parse)
+            .collect::<Result<Vec<Self>>>()?;
+        if identities.is_empty() {
+            anyhow::bail!("No age identity found in {}", path);
+        }
+        Ok(identities)
+    }
+
+    fn unwrap(&self, stanza: &Stanza) -> Result<Option<[u8; FILE_KEY_LEN]>> {
+        let [share] = stanza.args.as_slice() else {
+            anyhow::bail!("Malformed X25519 stanza");
+        };
+        let share: [u8; 32] = BASE64_STANDARD_NO_PAD
+            .decode(share)?
+            .try_into()
+            .map_err(|_| anyhow!("Malformed X25519 stanza"))?;
+        let share = PublicKey::from(share);
+        let shared = self.0.diffie_hellman(&share);
+        if !shared.was_contributory() {
+            anyhow::bail!("Malformed X25519 stanza");
+        }
+        let salt = [share.as_bytes().as_slice(), self.to_public().0.as_bytes()].concat();
+        let wrap_key = hkdf_sha256(&salt, shared.as_bytes(), X25519_LABEL);
+        Ok(open_file_key(&wrap_key, &stanza.body))
+    }
+}
+
+impl KeyGenerator for AgeIdentity {
+    /// An identity file in the age-keygen layout, and the matching recipient
+    fn generate() -> Result<Vec<Vec<u8>>> {
+        let identity = AgeIdentity(StaticSecret::random_from_rng(OsRng));
+        let recipient = identity.to_public();
+        let sk = format!(
+            "# created: {}\n# public key: {}\n{}\n",
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            recipient,
+            identity
+        );
+        Ok(vec![
+            sk.into_bytes(),
+            format!("{}\n", recipient).into_bytes(),
+        ])
+    }
+}
+
+impl Stanza {
+    fn write_to(&self, header: &mut String) {
+        header.push_str("->");
+        for arg in std::iter::once(&self.tag).chain(&self.args) {
+            header.push(' ');
+            header.push_str(arg);
+        }
+        header.push('\n');
+        let body = BASE64_STANDARD_NO_PAD.encode(&self.body);
+        for line in body.as_bytes().chunks(STANZA_COLUMNS) {
+            header.push_str(std::str::from_utf8(line).expect("base64 is ascii"));
+            header.push('\n');
+        }
+        // a full (or empty) last line would not end the body
+        if body.len().is_multiple_of(STANZA_COLUMNS) {
+            header.push('\n');
+        }
+    }
+}
+
+impl AgeEncryptor {
+    pub fn from_key(key: &EncryptionKey) -> Result<Self> {
+        match key {
+            EncryptionKey::Recipients(recipients) => Ok(AgeEncryptor::Recipients(
+                recipients
+                    .iter()
+                    .map(|r| r.parse())
+                    .collect::<Result<_>>()?,
+            )),
+            EncryptionKey::Passphrase(passphrase) => Ok(AgeEncryptor::Passphrase {
+                passphrase: passphrase.clone(),
+                log_n: SCRYPT_LOG_N,
+            }),
+            _ => anyhow::bail!("The age format needs --recipient or a passphrase"),
+        }
+    }
+
+    fn stanzas(&self, file_key: &[u8]) -> Result<Vec<Stanza>> {
+        match self {
+            AgeEncryptor::Recipients(recipients) => {
+                recipients.iter().map(|r| r.wrap(file_key)).collect()
+            }
+            AgeEncryptor::Passphrase { passphrase, log_n } => {
+                let mut salt = [0u8; SCRYPT_SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                let wrap_key = scrypt_key(passphrase, &salt, *log_n)?;
+                Ok(vec![Stanza {
+                    tag: "scrypt".to_string(),
+                    args: vec![BASE64_STANDARD_NO_PAD.encode(salt), log_n.to_string()],
+                    body: seal_file_key(&wrap_key, file_key)?,
+                }])
+            }
+        }
+    }
+}
+
+impl TextEncrypt for AgeEncryptor {
+    fn encrypt(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let mut file_key = [0u8; FILE_KEY_LEN];
+        OsRng.fill_bytes(&mut file_key);
+
+        let mut header = format!("{}\n", AGE_MAGIC);
+        for stanza in self.stanzas(&file_key)? {
+            stanza.write_to(&mut header);
+        }
+        header.push_str("---");
+        let mac = header_mac(&file_key, header.as_bytes())
+            .finalize()
+            .into_bytes();
+        writeln!(writer, "{} {}", header, BASE64_STANDARD_NO_PAD.encode(mac))?;
+
+        let mut nonce = [0u8; PAYLOAD_NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        writer.write_all(&nonce)?;
+        let payload = ChaCha20Poly1305::new(&hkdf_sha256(&nonce, &file_key, b"payload").into());
+        // age's 11 byte big-endian counter is a zero prefix followed by our 32-bit counter
+        seal_chunks(
+            &payload,
+            &vec![0u8; nonce_prefix_len(12)],
+            b"",
+            reader,
+            writer,
+        )
+    }
+}
+
+impl AgeDecryptor {
+    pub fn from_key(key: &EncryptionKey) -> Result<Self> {
+        match key {
+            EncryptionKey::Identities(paths) => {
+                let mut identities = Vec::new();
+                for path in paths {
+                    identities.extend(AgeIdentity::load_all(path)?);
+                }
+                Ok(AgeDecryptor::Identities(identities))
+            }
+            EncryptionKey::Passphrase(passphrase) => {
+                Ok(AgeDecryptor::Passphrase(passphrase.clone()))
+            }
+            _ => anyhow::bail!("Input is an age file, use --identity or --passphrase"),
+        }
+    }
+
+    fn file_key(&self, stanzas: &[Stanza]) -> Result<[u8; FILE_KEY_LEN]> {
+        let scrypt = stanzas.iter().any(|s| s.tag == "scrypt");
+        if scrypt && stanzas.len() != 1 {
+            anyhow::bail!("An age scrypt stanza must be the only one");
+        }
+        match self {
+            AgeDecryptor::Identities(_) if scrypt => {
+                anyhow::bail!("Input was encrypted with a passphrase, use --passphrase")
+            }
+            AgeDecryptor::Identities(identities) => {
+                for stanza in stanzas.iter().filter(|s| s.tag == "X25519") {
+                    for identity in identities {
+                        if let Some(file_key) = identity.unwrap(stanza)? {
+                            return Ok(file_key);
+                        }
+                    }
+                }
+                anyhow::bail!("No identity matches a recipient of the input")
+            }
+            AgeDecryptor::Passphrase(passphrase) => {
+                let [stanza] = stanzas else {
+                    anyhow::bail!("Input was encrypted to age recipients, use --identity");
+                };
+                let (true, [salt, log_n]) = (scrypt, stanza.args.as_slice()) else {
+                    anyhow::bail!("Input was encrypted to age recipients, use --identity");
+                };
+                let salt: [u8; SCRYPT_SALT_LEN] =
+                    BASE64_STANDARD_NO_PAD
+                        .decode(salt)?
+                        .try_into()
+                        .map_err(|_| anyhow!("Malformed scrypt stanza"))?;
+                let log_n: u8 = log_n
+                    .parse()
+                    .ok()
+                    .filter(|n: &u8| n.to_string() == *log_n)
+                    .ok_or_else(|| anyhow!("Malformed scrypt stanza"))?;
+                if log_n > MAX_SCRYPT_LOG_N {
+                    anyhow::bail!("scrypt work factor in the header is too large: {}", log_n);
+                }
+                let wrap_key = scrypt_key(passphrase, &salt, log_n)?;
+                open_file_key(&wrap_key, &stanza.body)
+                    .ok_or_else(|| anyhow!("Decryption failed: wrong passphrase"))
+            }
+        }
+    }
+}
+
+impl TextDecrypt for AgeDecryptor {
+    fn decrypt(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let mut reader = BufReader::new(reader);
+        let (header, stanzas, mac) = read_header(&mut reader)?;
+        let file_key = self.file_key(&stanzas)?;
+        header_mac(&file_key, &header)
+            .verify_slice(&mac)
+            .map_err(|_| anyhow!("age header MAC mismatch, the header was modified"))?;
+
+        let mut nonce = [0u8; PAYLOAD_NONCE_LEN];
+        reader
+            .read_exact(&mut nonce)
+            .map_err(|_| anyhow!("age payload is truncated"))?;
+        let payload = ChaCha20Poly1305::new(&hkdf_sha256(&nonce, &file_key, b"payload").into());
+        open_chunks(
+            &payload,
+            &vec![0u8; nonce_prefix_len(12)],
+            b"",
+            &mut reader,
+            writer,
+        )
+    }
+}
+
+/// Encrypt to the age v1 format, as PEM text when `armor` is set
+pub fn process_age_encrypt(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    key: &EncryptionKey,
+    armor: bool,
+) -> Result<()> {
+    let encryptor = AgeEncryptor::from_key(key)?;
+    if armor {
+        // armored output is meant for text sized inputs, so buffering it is fine
+        let mut sealed = Vec::new();
+        encryptor.encrypt(reader, &mut sealed)?;
+        process_pem_encode(&mut &sealed[..], writer, AGE_ARMOR_LABEL)
+    } else {
+        encryptor.encrypt(reader, writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Decrypt a raw or PEM armored age file
+pub fn process_age_decrypt(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    key: &EncryptionKey,
+    armored: bool,
+) -> Result<()> {
+    let decryptor = AgeDecryptor::from_key(key)?;
+    if armored {
+        let mut sealed = Vec::new();
+        process_pem_decode(reader, &mut sealed, Some(AGE_ARMOR_LABEL))?;
+        decryptor.decrypt(&mut &sealed[..], writer)?;
+    } else {
+        decryptor.decrypt(reader, writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Parse the header up to the MAC line, returning the MAC'd bytes, the stanzas and the MAC
+fn read_header(reader: &mut impl BufRead) -> Result<(Vec<u8>, Vec<Stanza>, Vec<u8>)> {
+    let mut header = Vec::new();
+    if read_line(reader, &mut header)? != AGE_MAGIC {
+        anyhow::bail!("Not an age v1 file");
+    }
+    let mut stanzas = Vec::new();
+    loop {
+        let line = read_line(reader, &mut header)?;
+        if let Some(mac) = line.strip_prefix("--- ") {
+            // the MAC covers the header up to and including "---"
+            header.truncate(header.len() - mac.len() - 2);
+            let mac = BASE64_STANDARD_NO_PAD
+                .decode(mac)
+                .map_err(|_| anyhow!("Malformed age header MAC"))?;
+            return Ok((header, stanzas, mac));
+        }
+        let mut args = line
+            .strip_prefix("-> ")
+            .ok_or_else(|| anyhow!("Malformed age header line: {}", line))?
+            .split(' ')
+            .map(String::from);
+        let tag = args.next().filter(|tag| !tag.is_empty());
+        let tag = tag.ok_or_else(|| anyhow!("Malformed age stanza: {}", line))?;
+        let mut body = String::new();
+        loop {
+            let line = read_line(reader, &mut header)?;
+            if line.len() > STANZA_COLUMNS {
+                anyhow::bail!("Malformed age stanza body");
+            }
+            body.push_str(&line);
+            if line.len() < STANZA_COLUMNS {
+                break;
+            }
+        }
+        stanzas.push(Stanza {
+            tag,
+            args: args.collect(),
+            body: BASE64_STANDARD_NO_PAD
+                .decode(body)
+                .map_err(|_| anyhow!("Malformed age stanza body"))?,
+        });
+    }
+}
+
+/// Read one `\n` terminated header line, also appending it to `header`
+fn read_line(reader: &mut impl BufRead, header: &mut Vec<u8>) -> Result<String> {
+    let mut line = Vec::new();
+    reader.take(MAX_HEADER_LINE).read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        anyhow::bail!("age header is truncated or malformed");
+    }
+    header.extend_from_slice(&line);
+    header.push(b'\n');
+    String::from_utf8(line).map_err(|_| anyhow!("age header is not ASCII"))
+}
+
+fn decode_bech32(value: &str, hrp: &str) -> Result<[u8; 32]> {
+    let (found, data, variant) = bech32::decode(value)?;
+    if found != hrp || variant != Variant::Bech32 {
+        anyhow::bail!("expected a {} key", hrp);
+    }
+    Vec::<u8>::from_base32(&data)?
+        .try_into()
+        .map_err(|_| anyhow!("expected 32 key bytes"))
+}
+
+fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut okm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, &mut okm)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    okm
+}
+
+fn header_mac(file_key: &[u8], header: &[u8]) -> Hmac<Sha256> {
+    let key = hkdf_sha256(&[], file_key, b"header");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("HMAC takes any key length");
+    mac.update(header);
+    mac
+}
+
+fn scrypt_key(passphrase: &str, salt: &[u8], log_n: u8) -> Result<[u8; 32]> {
+    let params = scrypt::Params::new(log_n, 8, 1, 32).map_err(|e| anyhow!(e.to_string()))?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(
+        passphrase.as_bytes(),
+        &[SCRYPT_LABEL, salt].concat(),
+        &params,
+        &mut key,
+    )
+    .map_err(|e| anyhow!(e.to_string()))?;
+    Ok(key)
+}
+
+/// Stanza bodies are the file key sealed under a single use key, so the nonce is all zeros
+fn seal_file_key(wrap_key: &[u8; 32], file_key: &[u8]) -> Result<Vec<u8>> {
+    ChaCha20Poly1305::new(wrap_key.into())
+        .encrypt(&Default::default(), file_key)
+        .map_err(|_| anyhow!("Encryption failed"))
+}
+
+fn open_file_key(wrap_key: &[u8; 32], body: &[u8]) -> Option<[u8; FILE_KEY_LEN]> {
+    ChaCha20Poly1305::new(wrap_key.into())
+        .decrypt(&Default::default(), body)
+        .ok()?
+        .try_into()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seal(encryptor: &AgeEncryptor, data: &[u8]) -> Result<Vec<u8>> {
+        let mut sealed = Vec::new();
+        encryptor.encrypt(&mut &data[..], &mut sealed)?;
+        Ok(sealed)
+    }
+
+    fn open(decryptor: &AgeDecryptor, sealed: &[u8]) -> Result<Vec<u8>> {
+        let mut plain = Vec::new();
+        decryptor.decrypt(&mut &sealed[..], &mut plain)?;
+        Ok(plain)
+    }
+
+    #[test]
+    fn test_age_x25519_recipients() -> Result<()> {
+        let alice: AgeIdentity = AgeIdentity::load_all("fixtures/age.key")?.remove(0);
+        let bob = AgeIdentity(StaticSecret::random_from_rng(OsRng));
+        let eve = AgeIdentity(StaticSecret::random_from_rng(OsRng));
+        let encryptor = AgeEncryptor::Recipients(vec![alice.to_public(), bob.to_public()]);
+        for len in [0, 1, 64 * 1024, 64 * 1024 + 1] {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let sealed = seal(&encryptor, &data)?;
+            for identity in [&alice, &bob] {
+                let decryptor = AgeDecryptor::Identities(vec![identity.clone()]);
+                assert_eq!(open(&decryptor, &sealed)?, data, "length {}", len);
+            }
+            assert!(open(&AgeDecryptor::Identities(vec![eve.clone()]), &sealed).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_age_scrypt_passphrase() -> Result<()> {
+        let encryptor = AgeEncryptor::Passphrase {
+            passphrase: "correct horse".to_string(),
+            log_n: 10,
+        };
+        let sealed = seal(&encryptor, b"hello age")?;
+        let decryptor = AgeDecryptor::Passphrase("correct horse".to_string());
+        assert_eq!(open(&decryptor, &sealed)?, b"hello age");
+        assert!(open(&AgeDecryptor::Passphrase("wrong".to_string()), &sealed).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_age_header_is_authenticated() -> Result<()> {
+        let identity = AgeIdentity::load_all("fixtures/age.key")?.remove(0);
+        let sealed = seal(&AgeEncryptor::Recipients(vec![identity.to_public()]), b"hi")?;
+        let decryptor = AgeDecryptor::Identities(vec![identity]);
+        // append a grease stanza, which decryptors ignore but the MAC covers
+        let mac_line = sealed.windows(4).position(|w| w == b"\n---").unwrap() + 1;
+        let mut modified = sealed[..mac_line].to_vec();
+        modified.extend_from_slice(b"-> grease\n\n");
+        modified.extend_from_slice(&sealed[mac_line..]);
+        assert!(open(&decryptor, &modified).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_age_interop_fixture() -> Result<()> {
+        // fixtures/hello.age was written by the age tool to the fixtures/age.key recipient
+        let identities = AgeIdentity::load_all("fixtures/age.key")?;
+        assert_eq!(
+            identities[0].to_public().to_string(),
+            fs::read_to_string("fixtures/age.pub")?.trim()
+        );
+        let sealed = fs::read("fixtures/hello.age")?;
+        let plain = open(&AgeDecryptor::Identities(identities), &sealed)?;
+        assert_eq!(plain, b"hello from age\n");
+        Ok(())
+    }
+}
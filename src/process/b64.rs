@@ -1,69 +1,416 @@
-use crate::{command::Base64Format, get_reader};
-use base64::{engine::GeneralPurpose, prelude::*};
-use std::io::Read;
+use crate::command::{Base64Format, CodecFormat};
+use crate::process::{is_positional, process_decode, process_encode, SkipWhitespace};
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    read::DecoderReader,
+};
+use std::io::{self, Read, Write};
 
-pub fn process_base64_encode(input: &str, format: Base64Format) -> anyhow::Result<String> {
-    let mut reader = get_reader(input)?;
+/// Longest input (in symbols) the quadratic positional codecs are tried on by `detect`
+const MAX_DETECT_POSITIONAL_LEN: usize = 4096;
 
+/// Standard alphabet that accepts input with or without padding
+const LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Stream `reader` into `writer` as base64, breaking lines every `wrap` characters (0 disables)
+pub fn process_base64_encode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    format: Base64Format,
+    wrap: usize,
+) -> anyhow::Result<()> {
+    let format = match format {
+        Base64Format::Standard => CodecFormat::Base64,
+        Base64Format::UrlSafe => CodecFormat::Base64UrlNoPad,
+        Base64Format::Auto => anyhow::bail!("auto is only supported for decoding"),
+    };
+    process_encode(reader, writer, format, wrap)
+}
+
+/// Stream base64 from `reader` into `writer`, ignoring any whitespace or line breaks
+pub fn process_base64_decode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    format: Base64Format,
+) -> anyhow::Result<()> {
+    match format {
+        Base64Format::Standard => process_decode(reader, writer, CodecFormat::Base64),
+        Base64Format::UrlSafe => process_decode(reader, writer, CodecFormat::Base64UrlNoPad),
+        Base64Format::Auto => {
+            let mut decoder = base64_auto_reader(reader);
+            io::copy(&mut decoder, writer)?;
+            writer.flush()?;
+            Ok(())
+        }
+    }
+}
+
+/// Streaming decoder for base64 in either alphabet, padded or not, ignoring whitespace
+pub fn base64_auto_reader<'a>(reader: impl Read + 'a) -> impl Read + 'a {
+    // both alphabets only differ in two symbols, so fold url-safe onto standard
+    let reader = UrlSafeToStandard::new(SkipWhitespace::new(reader));
+    DecoderReader::new(reader, &LENIENT)
+}
+
+/// Report every codec the input decodes under, with the decoded length. Base58 and base62 are
+/// only tried on inputs of up to `MAX_DETECT_POSITIONAL_LEN` symbols
+pub fn process_base64_detect(reader: &mut dyn Read) -> anyhow::Result<Vec<(CodecFormat, usize)>> {
     let mut buf = Vec::new();
     reader.read_to_end(&mut buf)?;
+    let symbols = buf.iter().filter(|b| !b.is_ascii_whitespace()).count();
+    if symbols == 0 {
+        anyhow::bail!("empty input");
+    }
+
+    let mut valid = Vec::new();
+    for format in CodecFormat::ALL {
+        if is_positional(format) && symbols > MAX_DETECT_POSITIONAL_LEN {
+            continue;
+        }
+        let mut decoded = Vec::new();
+        if process_decode(&mut &buf[..], &mut decoded, format).is_ok() {
+            valid.push((format, decoded.len()));
+        }
+    }
+    Ok(valid)
+}
+
+/// Emit `data:<mime>;base64,...`, sniffing the MIME type from magic bytes unless one is given
+pub fn process_datauri_encode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    mime: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let mime = mime.unwrap_or_else(|| sniff_mime(&buf));
+    write!(writer, "data:{};base64,", mime)?;
+    process_base64_encode(&mut &buf[..], writer, Base64Format::Standard, 0)
+}
 
-    let engine = get_engine(format);
+/// Parse a data URI, writing its payload to `writer` and returning the media type
+pub fn process_datauri_decode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+) -> anyhow::Result<String> {
+    let mut uri = String::new();
+    reader.read_to_string(&mut uri)?;
+    let uri = uri.trim();
+    let rest = uri
+        .get(..5)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("data:"))
+        .map(|_| &uri[5..])
+        .ok_or_else(|| anyhow::anyhow!("Not a data URI"))?;
+    let (header, data) = rest
+        .split_once(',')
+        .ok_or_else(|| anyhow::anyhow!("Data URI is missing the ',' separator"))?;
 
-    let encode = engine.encode(buf);
+    let (mime, base64) = match header.strip_suffix(";base64") {
+        Some(mime) => (mime, true),
+        None => (header, false),
+    };
+    if base64 {
+        process_base64_decode(&mut data.as_bytes(), writer, Base64Format::Auto)?;
+    } else {
+        let data: Vec<u8> = percent_encoding::percent_decode_str(data).collect();
+        writer.write_all(&data)?;
+        writer.flush()?;
+    }
+
+    // RFC 2397: an omitted media type means text/plain;charset=US-ASCII
+    Ok(match mime {
+        "" => "text/plain;charset=US-ASCII".to_string(),
+        m if m.starts_with(';') => format!("text/plain{}", m),
+        m => m.to_string(),
+    })
+}
 
-    Ok(encode)
+/// Wrap `reader` in PEM armor with the given label, 64 characters per line
+pub fn process_pem_encode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    label: &str,
+) -> anyhow::Result<()> {
+    writeln!(writer, "-----BEGIN {}-----", label)?;
+    process_base64_encode(reader, writer, Base64Format::Standard, 64)?;
+    writeln!(writer, "-----END {}-----", label)?;
+    writer.flush()?;
+    Ok(())
 }
 
-pub fn process_base64_decode(input: &str, format: Base64Format) -> anyhow::Result<Vec<u8>> {
-    let mut reader = get_reader(input)?;
+/// Decode every PEM block (optionally only those with `label`) into `writer`,
+/// returning the label and decoded length of each block
+pub fn process_pem_decode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    label: Option<&str>,
+) -> anyhow::Result<Vec<(String, usize)>> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
 
-    let mut buf = String::new();
-    reader.read_to_string(&mut buf)?;
-    let buf = buf.trim();
+    let mut blocks = Vec::new();
+    let mut lines = input.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let Some(begin) = pem_boundary(line, "BEGIN") else {
+            continue;
+        };
 
-    let engine = get_engine(format);
+        let mut body = String::new();
+        let mut terminated = false;
+        for line in lines.by_ref() {
+            if let Some(end) = pem_boundary(line, "END") {
+                if end != begin {
+                    anyhow::bail!("PEM block BEGIN {} ends with END {}", begin, end);
+                }
+                terminated = true;
+                break;
+            }
+            // skip RFC 1421 headers such as Proc-Type and DEK-Info
+            if !line.contains(':') {
+                body.push_str(line);
+            }
+        }
+        if !terminated {
+            anyhow::bail!("PEM block {} is not terminated", begin);
+        }
+        if label.is_some_and(|label| label != begin) {
+            continue;
+        }
+
+        let mut decoded = Vec::new();
+        process_base64_decode(&mut body.as_bytes(), &mut decoded, Base64Format::Standard)?;
+        writer.write_all(&decoded)?;
+        blocks.push((begin.to_string(), decoded.len()));
+    }
+    writer.flush()?;
 
-    let decode = engine.decode(buf)?;
-    Ok(decode)
+    if blocks.is_empty() {
+        match label {
+            Some(label) => anyhow::bail!("No PEM block labelled {}", label),
+            None => anyhow::bail!("No PEM block found"),
+        }
+    }
+    Ok(blocks)
 }
 
-fn get_engine(format: Base64Format) -> GeneralPurpose {
-    match format {
-        Base64Format::Standard => BASE64_STANDARD,
-        Base64Format::UrlSafe => BASE64_URL_SAFE_NO_PAD,
+fn pem_boundary<'a>(line: &'a str, kind: &str) -> Option<&'a str> {
+    line.strip_prefix("-----")?
+        .strip_prefix(kind)?
+        .strip_prefix(' ')?
+        .strip_suffix("-----")
+}
+
+fn sniff_mime(data: &[u8]) -> &'static str {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"\x00\x00\x01\x00", "image/x-icon"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+        (b"\xfd7zXZ\x00", "application/x-xz"),
+        (b"\x00asm", "application/wasm"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"ID3", "audio/mpeg"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+    ];
+
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return mime;
+    }
+    if data.len() >= 12 && &data[..4] == b"RIFF" {
+        match &data[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            _ => {}
+        }
+    }
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        return match &data[8..12] {
+            b"avif" => "image/avif",
+            b"heic" => "image/heic",
+            _ => "video/mp4",
+        };
+    }
+
+    match std::str::from_utf8(data) {
+        Ok(text) => {
+            let lower: String = text.trim_start().chars().take(256).collect();
+            let lower = lower.to_ascii_lowercase();
+            if lower.starts_with("<svg") || (lower.starts_with("<?xml") && lower.contains("<svg")) {
+                "image/svg+xml"
+            } else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+                "text/html"
+            } else if lower.starts_with("<?xml") {
+                "application/xml"
+            } else if serde_json::from_str::<serde_json::Value>(text).is_ok() {
+                "application/json"
+            } else {
+                "text/plain"
+            }
+        }
+        Err(_) => "application/octet-stream",
+    }
+}
+
+struct UrlSafeToStandard<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> UrlSafeToStandard<R> {
+    fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Read> Read for UrlSafeToStandard<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        for c in &mut buf[..n] {
+            match *c {
+                b'-' => *c = b'+',
+                b'_' => *c = b'/',
+                _ => {}
+            }
+        }
+        Ok(n)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::get_reader;
 
     #[test]
     fn test_process_base64_encode_standard() {
-        let input = "fixtures/base64/b64_original.txt";
+        let mut reader = get_reader("fixtures/base64/b64_original.txt").unwrap();
         let format = Base64Format::Standard;
-        assert!(process_base64_encode(input, format).is_ok());
+        assert!(process_base64_encode(&mut reader, &mut Vec::new(), format, 0).is_ok());
     }
 
     #[test]
     fn test_process_base64_encode_urlsafe() {
-        let input = "fixtures/base64/b64_original.txt";
+        let mut reader = get_reader("fixtures/base64/b64_original.txt").unwrap();
         let format = Base64Format::UrlSafe;
-        assert!(process_base64_encode(input, format).is_ok());
+        assert!(process_base64_encode(&mut reader, &mut Vec::new(), format, 0).is_ok());
     }
 
     #[test]
     fn test_process_base64_decode_standard() {
-        let input = "fixtures/base64/b64_standard.txt";
+        let mut reader = get_reader("fixtures/base64/b64_standard.txt").unwrap();
         let format = Base64Format::Standard;
-        assert!(process_base64_decode(input, format).is_ok());
+        assert!(process_base64_decode(&mut reader, &mut Vec::new(), format).is_ok());
     }
 
     #[test]
     fn test_process_base64_decode_urlsafe() {
-        let input = "fixtures/base64/b64_urlsafe.txt";
+        let mut reader = get_reader("fixtures/base64/b64_urlsafe.txt").unwrap();
         let format = Base64Format::UrlSafe;
-        assert!(process_base64_decode(input, format).is_ok());
+        assert!(process_base64_decode(&mut reader, &mut Vec::new(), format).is_ok());
+    }
+
+    #[test]
+    fn test_process_base64_decode_auto() -> anyhow::Result<()> {
+        let data = [0xfb, 0xff, 0x01, 0x02];
+        for encoded in ["+/8BAg==", "+/8BAg", "-_8BAg==", "-_8BAg", " -_8B\nAg\n"] {
+            let mut decoded = Vec::new();
+            process_base64_decode(&mut encoded.as_bytes(), &mut decoded, Base64Format::Auto)?;
+            assert_eq!(decoded, data, "{}", encoded);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_base64_detect() -> anyhow::Result<()> {
+        let detected = process_base64_detect(&mut "-_8BAg".as_bytes())?;
+        let names: Vec<String> = detected.iter().map(|(f, _)| f.to_string()).collect();
+        assert!(names.contains(&"base64url-nopad".to_string()));
+        assert!(!names.contains(&"base64".to_string()));
+        assert!(!names.contains(&"base64url".to_string()));
+
+        let err = process_base64_detect(&mut " \n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "empty input");
+
+        let long = "2".repeat(MAX_DETECT_POSITIONAL_LEN + 1);
+        let detected = process_base64_detect(&mut long.as_bytes())?;
+        assert!(detected.iter().all(|(f, _)| !is_positional(*f)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_base64_decode_binary() -> anyhow::Result<()> {
+        let key = std::fs::read("fixtures/ed25519.sk")?;
+
+        let mut encoded = Vec::new();
+        process_base64_encode(&mut &key[..], &mut encoded, Base64Format::Standard, 0)?;
+        let mut decoded = Vec::new();
+        process_base64_decode(&mut &encoded[..], &mut decoded, Base64Format::Standard)?;
+        assert_eq!(decoded, key);
+        Ok(())
+    }
+
+    #[test]
+    fn test_datauri_roundtrip() -> anyhow::Result<()> {
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+        let mut uri = Vec::new();
+        process_datauri_encode(&mut &png[..], &mut uri, None)?;
+        assert!(uri.starts_with(b"data:image/png;base64,iVBORw0KGgo"));
+
+        let mut decoded = Vec::new();
+        let mime = process_datauri_decode(&mut &uri[..], &mut decoded)?;
+        assert_eq!(mime, "image/png");
+        assert_eq!(decoded, png);
+
+        let mut decoded = Vec::new();
+        let mime = process_datauri_decode(&mut "data:,A%20brief%20note".as_bytes(), &mut decoded)?;
+        assert_eq!(mime, "text/plain;charset=US-ASCII");
+        assert_eq!(decoded, b"A brief note");
+        Ok(())
+    }
+
+    #[test]
+    fn test_pem_bundle() -> anyhow::Result<()> {
+        let key = std::fs::read("fixtures/ed25519.pk")?;
+        let mut bundle = Vec::new();
+        process_pem_encode(&mut &key[..], &mut bundle, "PUBLIC KEY")?;
+        process_pem_encode(&mut &b"second"[..], &mut bundle, "CERTIFICATE")?;
+
+        let mut decoded = Vec::new();
+        let blocks = process_pem_decode(&mut &bundle[..], &mut decoded, None)?;
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(decoded, [&key[..], b"second"].concat());
+
+        let mut decoded = Vec::new();
+        process_pem_decode(&mut &bundle[..], &mut decoded, Some("PUBLIC KEY"))?;
+        assert_eq!(decoded, key);
+
+        assert!(process_pem_decode(&mut &bundle[..], &mut Vec::new(), Some("X")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_base64_wrap_roundtrip() -> anyhow::Result<()> {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+
+        let mut encoded = Vec::new();
+        process_base64_encode(&mut &data[..], &mut encoded, Base64Format::Standard, 76)?;
+        let lines: Vec<&[u8]> = encoded.split(|c| *c == b'\n').collect();
+        assert!(lines[..lines.len() - 2].iter().all(|l| l.len() == 76));
+
+        let mut decoded = Vec::new();
+        process_base64_decode(&mut &encoded[..], &mut decoded, Base64Format::Standard)?;
+        assert_eq!(decoded, data);
+        Ok(())
     }
 }
@@ -0,0 +1,271 @@
+use anyhow::Result;
+use sha1::{Digest, Sha1};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+const BLOOM_MAGIC: &[u8; 8] = b"RCLIBLM1";
+const BLOOM_HEADER_LEN: u64 = 8 + 8 + 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreachedStatus {
+    NotFound,
+    /// The bloom index matched but there is no dataset to confirm against
+    Possibly,
+    Found(u64),
+}
+
+/// A Pwned Passwords SHA-1 file (`HASH:COUNT` lines, sorted by hash) searched in place
+pub struct PwnedDataset {
+    reader: BufReader<File>,
+    len: u64,
+}
+
+/// A bloom filter over the SHA-1 hashes of a dataset, queried with a few seeks
+pub struct BloomIndex {
+    file: File,
+    bits: u64,
+    hashes: u32,
+}
+
+impl PwnedDataset {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            reader: BufReader::new(file),
+            len,
+        })
+    }
+
+    /// Binary search the file for the hash, returning its breach count
+    pub fn lookup(&mut self, hash: &[u8; 20]) -> Result<Option<u64>> {
+        let target = hex_upper(hash);
+        let (mut lo, mut hi) = (0, self.len);
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let start = self.line_start(mid)?;
+            if start >= hi {
+                hi = mid;
+                continue;
+            }
+
+            let mut line = String::new();
+            let read = self.reader.read_line(&mut line)? as u64;
+            let line = line.trim_end();
+            let (prefix, count) = dataset_line(line)?;
+
+            match prefix.to_ascii_uppercase().as_str().cmp(target.as_str()) {
+                std::cmp::Ordering::Equal => return Ok(Some(count.trim().parse()?)),
+                std::cmp::Ordering::Less => lo = start + read,
+                std::cmp::Ordering::Greater => hi = start,
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Position the reader on the first line starting at or after `pos`
+    fn line_start(&mut self, pos: u64) -> Result<u64> {
+        if pos == 0 {
+            self.reader.seek(SeekFrom::Start(0))?;
+            return Ok(0);
+        }
+        self.reader.seek(SeekFrom::Start(pos - 1))?;
+        let mut skipped = Vec::new();
+        let read = self.reader.read_until(b'\n', &mut skipped)? as u64;
+        Ok(pos - 1 + read)
+    }
+}
+
+impl BloomIndex {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let mut header = [0u8; BLOOM_HEADER_LEN as usize];
+        file.read_exact(&mut header)?;
+        if &header[..8] != BLOOM_MAGIC {
+            anyhow::bail!("Not a breached password index");
+        }
+        let bits = u64::from_le_bytes(header[8..16].try_into()?);
+        let hashes = u32::from_le_bytes(header[16..20].try_into()?);
+        Ok(Self { file, bits, hashes })
+    }
+
+    pub fn contains(&mut self, hash: &[u8; 20]) -> Result<bool> {
+        for bit in bloom_bits(hash, self.bits, self.hashes) {
+            let mut byte = [0u8; 1];
+            self.file
+                .seek(SeekFrom::Start(BLOOM_HEADER_LEN + bit / 8))?;
+            self.file.read_exact(&mut byte)?;
+            if byte[0] & (1 << (bit % 8)) == 0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Build an index for every hash in the dataset with the given false positive rate
+    pub fn build(dataset: impl AsRef<Path>, output: impl AsRef<Path>, fp_rate: f64) -> Result<u64> {
+        if !(0.0..1.0).contains(&fp_rate) || fp_rate == 0.0 {
+            anyhow::bail!("False positive rate must be between 0 and 1");
+        }
+
+        let count = BufReader::new(File::open(&dataset)?).lines().count() as u64;
+        let n = count.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = ((-n * fp_rate.ln() / (ln2 * ln2)).ceil() as u64).max(8);
+        let hashes = ((bits as f64 / n * ln2).round() as u32).max(1);
+
+        let mut filter = vec![0u8; bits.div_ceil(8) as usize];
+        for line in BufReader::new(File::open(&dataset)?).lines() {
+            let line = line?;
+            let (prefix, _) = dataset_line(line.trim_end())?;
+            let hash = parse_hex_hash(prefix)?;
+            for bit in bloom_bits(&hash, bits, hashes) {
+                filter[(bit / 8) as usize] |= 1 << (bit % 8);
+            }
+        }
+
+        let mut writer = BufWriter::new(File::create(output)?);
+        writer.write_all(BLOOM_MAGIC)?;
+        writer.write_all(&bits.to_le_bytes())?;
+        writer.write_all(&hashes.to_le_bytes())?;
+        writer.write_all(&filter)?;
+        writer.flush()?;
+
+        Ok(count)
+    }
+}
+
+/// Double hashing over the (already uniform) SHA-1 digest
+fn bloom_bits(hash: &[u8; 20], bits: u64, hashes: u32) -> impl Iterator<Item = u64> {
+    let h1 = u64::from_le_bytes(hash[..8].try_into().expect("slice is 8 bytes"));
+    let h2 = u64::from_le_bytes(hash[8..16].try_into().expect("slice is 8 bytes"));
+    (0..hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bits)
+}
+
+/// Split a `HASH:COUNT` line, refusing the 35-character suffixes of k-anonymity range files
+fn dataset_line(line: &str) -> Result<(&str, &str)> {
+    let (hash, count) = line.split_once(':').unwrap_or((line, "0"));
+    let hash = hash.trim();
+    if hash.len() != 40 {
+        anyhow::bail!(
+            "Expected a full 40-character SHA-1 hash, got {:?} (k-anonymity range files are not supported)",
+            hash
+        );
+    }
+    Ok((hash, count))
+}
+
+fn hex_upper(hash: &[u8; 20]) -> String {
+    hash.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn parse_hex_hash(hex: &str) -> Result<[u8; 20]> {
+    let bytes = data_encoding::HEXUPPER_PERMISSIVE.decode(hex.as_bytes())?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid SHA-1 hash: {}", hex))
+}
+
+pub fn process_breached_check(
+    password: &str,
+    dataset: Option<&str>,
+    index: Option<&str>,
+) -> Result<BreachedStatus> {
+    if dataset.is_none() && index.is_none() {
+        anyhow::bail!("Either a dataset or an index is required");
+    }
+
+    let hash: [u8; 20] = Sha1::digest(password.as_bytes()).into();
+
+    if let Some(index) = index {
+        if !BloomIndex::open(index)?.contains(&hash)? {
+            return Ok(BreachedStatus::NotFound);
+        }
+    }
+
+    match dataset {
+        Some(dataset) => match PwnedDataset::open(dataset)?.lookup(&hash)? {
+            Some(count) => Ok(BreachedStatus::Found(count)),
+            None => Ok(BreachedStatus::NotFound),
+        },
+        None => Ok(BreachedStatus::Possibly),
+    }
+}
+
+pub fn process_breached_index(dataset: &str, output: &str, fp_rate: f64) -> Result<u64> {
+    BloomIndex::build(dataset, output, fp_rate)
+}
+
+impl fmt::Display for BreachedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreachedStatus::NotFound => write!(f, "not found"),
+            BreachedStatus::Possibly => write!(f, "possibly breached (index match)"),
+            BreachedStatus::Found(count) => write!(f, "breached ({} times)", count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATASET: &str = "fixtures/pwned.txt";
+
+    #[test]
+    fn test_breached_dataset_lookup() -> Result<()> {
+        for (password, count) in [
+            ("football", 561925),
+            ("password", 9659365),
+            ("hunter2", 30257),
+            ("123456", 37359195),
+        ] {
+            assert_eq!(
+                process_breached_check(password, Some(DATASET), None)?,
+                BreachedStatus::Found(count)
+            );
+        }
+        assert_eq!(
+            process_breached_check("correct horse battery staple", Some(DATASET), None)?,
+            BreachedStatus::NotFound
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_breached_bloom_index() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let index = dir.path().join("pwned.bloom");
+        let index = index.to_str().unwrap();
+        assert_eq!(process_breached_index(DATASET, index, 0.001)?, 12);
+
+        assert_eq!(
+            process_breached_check("letmein", None, Some(index))?,
+            BreachedStatus::Possibly
+        );
+        assert_eq!(
+            process_breached_check("letmein", Some(DATASET), Some(index))?,
+            BreachedStatus::Found(1133640)
+        );
+        assert_eq!(
+            process_breached_check("not-in-the-list", Some(DATASET), Some(index))?,
+            BreachedStatus::NotFound
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_breached_rejects_range_suffixes() {
+        assert!(dataset_line("0018A45C4D1DEF81644B54AB7F969B88D65:10").is_err());
+        assert_eq!(
+            dataset_line("000000005AD76BD555C1D6D771DE417A4B87E4B4:10").unwrap(),
+            ("000000005AD76BD555C1D6D771DE417A4B87E4B4", "10")
+        );
+    }
+}
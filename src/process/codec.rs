@@ -0,0 +1,556 @@
+use crate::command::CodecFormat;
+use anyhow::Result;
+use base64::{engine::GeneralPurpose, prelude::*, read::DecoderReader, write::EncoderWriter};
+use data_encoding::{Encoding, Specification, BASE32, BASE32HEX, HEXLOWER_PERMISSIVE};
+use std::io::{self, Read, Write};
+
+const BASE58_BITCOIN: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE58_FLICKR: &[u8] = b"123456789abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ";
+const BASE62: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const ASCII85: &[u8] =
+    b"!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstu";
+const Z85: &[u8] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+const RFC1924: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&()*+-;<=>?@^_`{|}~";
+
+// how many blocks each streaming read covers
+const BLOCKS_PER_CHUNK: usize = 1024;
+
+pub trait Codec {
+    /// Encode everything from `reader` into `writer`
+    fn encode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()>;
+    /// Decode everything from `reader` (whitespace already removed) into `writer`
+    fn decode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()>;
+}
+
+/// Block based RFC 4648 style codecs backed by `data-encoding`
+struct BlockCodec {
+    encoding: Encoding,
+    // bytes per encoded block and symbols per decoded block
+    bytes: usize,
+    symbols: usize,
+}
+
+struct Base64Codec {
+    engine: GeneralPurpose,
+}
+
+/// Positional (big number) codecs, which need the whole input in memory
+struct BaseXCodec {
+    alphabet: &'static [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Base85Variant {
+    Ascii85,
+    Z85,
+    Rfc1924,
+}
+
+struct Base85Codec {
+    variant: Base85Variant,
+}
+
+pub fn get_codec(format: CodecFormat) -> Box<dyn Codec> {
+    match format {
+        CodecFormat::Base16 => Box::new(BlockCodec::new(HEXLOWER_PERMISSIVE.clone(), 1, 2)),
+        CodecFormat::Base32 => Box::new(BlockCodec::new(BASE32.clone(), 5, 8)),
+        CodecFormat::Base32Hex => Box::new(BlockCodec::new(BASE32HEX.clone(), 5, 8)),
+        CodecFormat::Base32Crockford => Box::new(BlockCodec::new(crockford(), 5, 8)),
+        CodecFormat::Base58 => Box::new(BaseXCodec::new(BASE58_BITCOIN)),
+        CodecFormat::Base58Flickr => Box::new(BaseXCodec::new(BASE58_FLICKR)),
+        CodecFormat::Base62 => Box::new(BaseXCodec::new(BASE62)),
+        CodecFormat::Ascii85 => Box::new(Base85Codec::new(Base85Variant::Ascii85)),
+        CodecFormat::Z85 => Box::new(Base85Codec::new(Base85Variant::Z85)),
+        CodecFormat::Base85 => Box::new(Base85Codec::new(Base85Variant::Rfc1924)),
+        CodecFormat::Base64 => Box::new(Base64Codec::new(BASE64_STANDARD)),
+        CodecFormat::Base64NoPad => Box::new(Base64Codec::new(BASE64_STANDARD_NO_PAD)),
+        CodecFormat::Base64Url => Box::new(Base64Codec::new(BASE64_URL_SAFE)),
+        CodecFormat::Base64UrlNoPad => Box::new(Base64Codec::new(BASE64_URL_SAFE_NO_PAD)),
+    }
+}
+
+/// Whether the codec is a positional (big number) one, whose decoding is quadratic in the input
+pub fn is_positional(format: CodecFormat) -> bool {
+    matches!(
+        format,
+        CodecFormat::Base58 | CodecFormat::Base58Flickr | CodecFormat::Base62
+    )
+}
+
+/// Stream `reader` into `writer` with the given codec, breaking lines every `wrap` characters
+pub fn process_encode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    format: CodecFormat,
+    wrap: usize,
+) -> Result<()> {
+    let mut wrapped = LineWrapWriter::new(writer, wrap);
+    get_codec(format).encode(reader, &mut wrapped)?;
+    wrapped.finish()?;
+    Ok(())
+}
+
+/// Stream encoded text from `reader` into `writer`, ignoring any whitespace or line breaks
+pub fn process_decode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    format: CodecFormat,
+) -> Result<()> {
+    get_codec(format).decode(&mut SkipWhitespace::new(reader), writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+impl BlockCodec {
+    fn new(encoding: Encoding, bytes: usize, symbols: usize) -> Self {
+        Self {
+            encoding,
+            bytes,
+            symbols,
+        }
+    }
+}
+
+impl Codec for BlockCodec {
+    fn encode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        for_each_chunk(reader, self.bytes * BLOCKS_PER_CHUNK, |chunk| {
+            writer.write_all(self.encoding.encode(chunk).as_bytes())?;
+            Ok(())
+        })
+    }
+
+    fn decode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        for_each_chunk(reader, self.symbols * BLOCKS_PER_CHUNK, |chunk| {
+            writer.write_all(&self.encoding.decode(chunk)?)?;
+            Ok(())
+        })
+    }
+}
+
+impl Base64Codec {
+    fn new(engine: GeneralPurpose) -> Self {
+        Self { engine }
+    }
+}
+
+impl Codec for Base64Codec {
+    fn encode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let mut encoder = EncoderWriter::new(writer, &self.engine);
+        io::copy(reader, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    fn decode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let mut decoder = DecoderReader::new(reader, &self.engine);
+        io::copy(&mut decoder, writer)?;
+        Ok(())
+    }
+}
+
+impl BaseXCodec {
+    fn new(alphabet: &'static [u8]) -> Self {
+        Self { alphabet }
+    }
+}
+
+impl Codec for BaseXCodec {
+    fn encode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let mut input = Vec::new();
+        reader.read_to_end(&mut input)?;
+
+        let base = self.alphabet.len() as u32;
+        let zeros = input.iter().take_while(|b| **b == 0).count();
+        // little endian digits in the target base
+        let mut digits: Vec<u32> = Vec::new();
+        for byte in &input[zeros..] {
+            let mut carry = *byte as u32;
+            for digit in digits.iter_mut() {
+                carry += *digit << 8;
+                *digit = carry % base;
+                carry /= base;
+            }
+            while carry > 0 {
+                digits.push(carry % base);
+                carry /= base;
+            }
+        }
+
+        let mut output = vec![self.alphabet[0]; zeros];
+        output.extend(digits.iter().rev().map(|d| self.alphabet[*d as usize]));
+        writer.write_all(&output)?;
+        Ok(())
+    }
+
+    fn decode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let mut input = Vec::new();
+        reader.read_to_end(&mut input)?;
+
+        let base = self.alphabet.len() as u32;
+        let table = reverse_table(self.alphabet);
+        // check the alphabet up front so bad input fails before the quadratic conversion
+        if let Some(c) = input.iter().find(|c| table[**c as usize] == INVALID) {
+            anyhow::bail!("Invalid character: {:?}", *c as char);
+        }
+        let zeros = input.iter().take_while(|c| **c == self.alphabet[0]).count();
+        // little endian bytes
+        let mut bytes: Vec<u32> = Vec::new();
+        for c in &input[zeros..] {
+            let mut carry = table[*c as usize] as u32;
+            for byte in bytes.iter_mut() {
+                carry += *byte * base;
+                *byte = carry & 0xff;
+                carry >>= 8;
+            }
+            while carry > 0 {
+                bytes.push(carry & 0xff);
+                carry >>= 8;
+            }
+        }
+
+        let mut output = vec![0u8; zeros];
+        output.extend(bytes.iter().rev().map(|b| *b as u8));
+        writer.write_all(&output)?;
+        Ok(())
+    }
+}
+
+impl Base85Codec {
+    fn new(variant: Base85Variant) -> Self {
+        Self { variant }
+    }
+
+    fn alphabet(&self) -> &'static [u8] {
+        match self.variant {
+            Base85Variant::Ascii85 => ASCII85,
+            Base85Variant::Z85 => Z85,
+            Base85Variant::Rfc1924 => RFC1924,
+        }
+    }
+
+    fn encode_group(&self, group: &[u8], output: &mut Vec<u8>) -> Result<()> {
+        if group.len() < 4 && self.variant == Base85Variant::Z85 {
+            anyhow::bail!("Z85 input length must be a multiple of 4");
+        }
+        let mut padded = [0u8; 4];
+        padded[..group.len()].copy_from_slice(group);
+        let mut value = u32::from_be_bytes(padded);
+
+        if value == 0 && group.len() == 4 && self.variant == Base85Variant::Ascii85 {
+            output.push(b'z');
+            return Ok(());
+        }
+
+        let mut chars = [0u8; 5];
+        for c in chars.iter_mut().rev() {
+            *c = self.alphabet()[(value % 85) as usize];
+            value /= 85;
+        }
+        output.extend_from_slice(&chars[..group.len() + 1]);
+        Ok(())
+    }
+}
+
+impl Codec for Base85Codec {
+    fn encode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        for_each_chunk(reader, 4 * BLOCKS_PER_CHUNK, |chunk| {
+            let mut output = Vec::with_capacity(chunk.len() / 4 * 5 + 5);
+            for group in chunk.chunks(4) {
+                self.encode_group(group, &mut output)?;
+            }
+            writer.write_all(&output)?;
+            Ok(())
+        })
+    }
+
+    fn decode(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let table = reverse_table(self.alphabet());
+        let ascii85 = self.variant == Base85Variant::Ascii85;
+
+        let mut digits = [0u8; 5];
+        let mut count = 0;
+        // ascii85 may be framed as <~ ... ~>
+        let mut seen = 0;
+        let mut pending_lt = false;
+        let mut output = Vec::new();
+
+        let flush_group = |digits: &[u8; 5], output: &mut Vec<u8>| -> Result<()> {
+            let value = digits.iter().fold(0u64, |acc, d| acc * 85 + *d as u64);
+            if value > u32::MAX as u64 {
+                anyhow::bail!("Base85 group overflows 32 bits");
+            }
+            output.extend_from_slice(&(value as u32).to_be_bytes());
+            Ok(())
+        };
+
+        let mut buf = vec![0u8; 5 * BLOCKS_PER_CHUNK];
+        'outer: loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            for &c in &buf[..n] {
+                seen += 1;
+                if ascii85 {
+                    if seen == 1 && c == b'<' {
+                        pending_lt = true;
+                        continue;
+                    }
+                    if pending_lt {
+                        pending_lt = false;
+                        if c == b'~' {
+                            continue;
+                        }
+                        digits[count] = table[b'<' as usize];
+                        count += 1;
+                    }
+                    if c == b'~' {
+                        break 'outer;
+                    }
+                    if c == b'z' && count == 0 {
+                        output.extend_from_slice(&[0u8; 4]);
+                        continue;
+                    }
+                }
+
+                let value = table[c as usize];
+                if value == INVALID {
+                    anyhow::bail!("Invalid character: {:?}", c as char);
+                }
+                digits[count] = value;
+                count += 1;
+                if count == 5 {
+                    flush_group(&digits, &mut output)?;
+                    count = 0;
+                }
+            }
+            writer.write_all(&output)?;
+            output.clear();
+        }
+
+        if pending_lt {
+            digits[count] = table[b'<' as usize];
+            count += 1;
+        }
+        match count {
+            0 => {}
+            1 => anyhow::bail!("Truncated base85 input"),
+            _ if self.variant == Base85Variant::Z85 => {
+                anyhow::bail!("Z85 input length must be a multiple of 5")
+            }
+            _ => {
+                // pad with the highest digit and drop the extra bytes
+                let kept = count - 1;
+                digits[count..].fill(84);
+                flush_group(&digits, &mut output)?;
+                output.truncate(output.len() - (4 - kept));
+            }
+        }
+        writer.write_all(&output)?;
+        Ok(())
+    }
+}
+
+fn crockford() -> Encoding {
+    let mut spec = Specification::new();
+    spec.symbols.push_str("0123456789ABCDEFGHJKMNPQRSTVWXYZ");
+    spec.translate.from.push_str("abcdefghjkmnpqrstvwxyzIiLlOo");
+    spec.translate.to.push_str("ABCDEFGHJKMNPQRSTVWXYZ111100");
+    spec.encoding().expect("crockford specification is valid")
+}
+
+const INVALID: u8 = 0xff;
+
+fn reverse_table(alphabet: &[u8]) -> [u8; 256] {
+    let mut table = [INVALID; 256];
+    for (i, c) in alphabet.iter().enumerate() {
+        table[*c as usize] = i as u8;
+    }
+    table
+}
+
+/// Feed `reader` to `f` in chunks of exactly `size` bytes, except for the final one
+fn for_each_chunk(
+    reader: &mut dyn Read,
+    size: usize,
+    mut f: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut buf = vec![0u8; size];
+    let mut filled = 0;
+    loop {
+        let n = reader.read(&mut buf[filled..])?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+        if filled == size {
+            f(&buf)?;
+            filled = 0;
+        }
+    }
+    f(&buf[..filled])
+}
+
+/// Inserts a newline every `width` bytes and terminates the output with one
+pub(crate) struct LineWrapWriter<W: Write> {
+    inner: W,
+    width: usize,
+    column: usize,
+}
+
+impl<W: Write> LineWrapWriter<W> {
+    pub(crate) fn new(inner: W, width: usize) -> Self {
+        Self {
+            inner,
+            width,
+            column: 0,
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(b"\n")?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for LineWrapWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.width == 0 {
+            return self.inner.write(buf);
+        }
+
+        let mut written = 0;
+        while written < buf.len() {
+            if self.column == self.width {
+                self.inner.write_all(b"\n")?;
+                self.column = 0;
+            }
+            let n = (self.width - self.column).min(buf.len() - written);
+            self.inner.write_all(&buf[written..written + n])?;
+            self.column += n;
+            written += n;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Drops ASCII whitespace so wrapped or indented input decodes as one stream
+pub(crate) struct SkipWhitespace<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> SkipWhitespace<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Read> Read for SkipWhitespace<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.inner.read(buf)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            let mut kept = 0;
+            for i in 0..n {
+                if !buf[i].is_ascii_whitespace() {
+                    buf[kept] = buf[i];
+                    kept += 1;
+                }
+            }
+            // only whitespace in this chunk, keep reading rather than signal EOF
+            if kept > 0 {
+                return Ok(kept);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(data: &[u8], format: CodecFormat) -> Result<String> {
+        let mut output = Vec::new();
+        get_codec(format).encode(&mut &data[..], &mut output)?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    fn decode(data: &str, format: CodecFormat) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        process_decode(&mut data.as_bytes(), &mut output, format)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn test_codec_known_vectors() -> Result<()> {
+        let vectors: &[(CodecFormat, &[u8], &str)] = &[
+            (CodecFormat::Base16, b"foobar", "666f6f626172"),
+            (CodecFormat::Base32, b"foobar", "MZXW6YTBOI======"),
+            (CodecFormat::Base32Hex, b"foobar", "CPNMUOJ1E8======"),
+            (CodecFormat::Base58, b"Hello World!", "2NEpo7TZRRrLZSi2U"),
+            (
+                CodecFormat::Base58,
+                &[0, 0, 0x28, 0x7f, 0xb4, 0xcd],
+                "11233QC4",
+            ),
+            (
+                CodecFormat::Z85,
+                &[0x86, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b],
+                "HelloWorld",
+            ),
+            (CodecFormat::Ascii85, b"Man is d", "9jqo^BlbD-"),
+            (CodecFormat::Ascii85, &[0, 0, 0, 0, 1], "z!<"),
+            (CodecFormat::Base64, b"fo", "Zm8="),
+            (CodecFormat::Base64NoPad, b"fo", "Zm8"),
+            (CodecFormat::Base64Url, &[0xfb, 0xff], "-_8="),
+            (CodecFormat::Base64UrlNoPad, &[0xfb, 0xff], "-_8"),
+        ];
+        for (format, data, expected) in vectors {
+            assert_eq!(encode(data, *format)?, *expected, "{}", format);
+            assert_eq!(decode(expected, *format)?, *data, "{}", format);
+        }
+
+        assert_eq!(decode("<~9jqo^BlbD-~>", CodecFormat::Ascii85)?, b"Man is d");
+        assert_eq!(
+            decode("csqpyrk1", CodecFormat::Base32Crockford)?,
+            decode("CSQPYRK1", CodecFormat::Base32Crockford)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_roundtrip() -> Result<()> {
+        let data: Vec<u8> = (0..=255u8).cycle().skip(3).take(5000).collect();
+        for format in [
+            CodecFormat::Base16,
+            CodecFormat::Base32,
+            CodecFormat::Base32Hex,
+            CodecFormat::Base32Crockford,
+            CodecFormat::Base58,
+            CodecFormat::Base58Flickr,
+            CodecFormat::Base62,
+            CodecFormat::Ascii85,
+            CodecFormat::Base85,
+            CodecFormat::Base64,
+            CodecFormat::Base64UrlNoPad,
+        ] {
+            let mut encoded = Vec::new();
+            process_encode(&mut &data[..], &mut encoded, format, 76)?;
+            let mut decoded = Vec::new();
+            process_decode(&mut &encoded[..], &mut decoded, format)?;
+            assert_eq!(decoded, data, "{}", format);
+        }
+
+        assert!(encode(b"abc", CodecFormat::Z85).is_err());
+        Ok(())
+    }
+}
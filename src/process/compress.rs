@@ -0,0 +1,212 @@
+use crate::command::{Base64Format, CompressAlgorithm};
+use crate::process::{process_base64_decode, process_base64_encode};
+use anyhow::Result;
+use flate2::{
+    read::{MultiGzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use std::io::{self, Read, Write};
+use xz2::{read::XzDecoder, write::XzEncoder};
+
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_WINDOW: u32 = 22;
+
+/// Stream `reader` through the compressor into `writer`, using the algorithm's default level if none is given
+pub fn process_compress(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    algorithm: CompressAlgorithm,
+    level: Option<u32>,
+) -> Result<()> {
+    let (min, max, default) = algorithm.levels();
+    let level = level.unwrap_or(default);
+    if !(min..=max).contains(&level) {
+        anyhow::bail!("{} level must be between {} and {}", algorithm, min, max);
+    }
+
+    match algorithm {
+        CompressAlgorithm::Gzip => {
+            let mut encoder = GzEncoder::new(&mut *writer, Compression::new(level));
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+        CompressAlgorithm::Deflate => {
+            let mut encoder = ZlibEncoder::new(&mut *writer, Compression::new(level));
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+        CompressAlgorithm::Zstd => {
+            let mut encoder = zstd::Encoder::new(&mut *writer, level as i32)?;
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+        CompressAlgorithm::Brotli => {
+            let mut encoder = brotli::CompressorWriter::new(
+                &mut *writer,
+                BROTLI_BUFFER_SIZE,
+                level,
+                BROTLI_WINDOW,
+            );
+            io::copy(reader, &mut encoder)?;
+            // into_inner finishes the stream
+            encoder.into_inner();
+        }
+        CompressAlgorithm::Xz => {
+            let mut encoder = XzEncoder::new(&mut *writer, level);
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decompress `reader` into `writer`, detecting the algorithm from magic bytes when not given.
+/// Brotli has no magic bytes, so it must always be given.
+pub fn process_decompress(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    algorithm: Option<CompressAlgorithm>,
+) -> Result<CompressAlgorithm> {
+    let mut magic = [0u8; 6];
+    let n = read_magic(reader, &mut magic)?;
+    let algorithm = match algorithm.or_else(|| detect_algorithm(&magic[..n])) {
+        Some(algorithm) => algorithm,
+        None => anyhow::bail!("Unknown format, pass --algorithm (brotli has no magic bytes)"),
+    };
+    let mut reader = (&magic[..n]).chain(reader);
+
+    let result = match algorithm {
+        CompressAlgorithm::Gzip => io::copy(&mut MultiGzDecoder::new(reader), writer),
+        CompressAlgorithm::Deflate => io::copy(&mut ZlibDecoder::new(reader), writer),
+        CompressAlgorithm::Zstd => io::copy(&mut zstd::Decoder::new(reader)?, writer),
+        CompressAlgorithm::Brotli => io::copy(
+            &mut brotli::Decompressor::new(&mut reader, BROTLI_BUFFER_SIZE),
+            writer,
+        ),
+        CompressAlgorithm::Xz => io::copy(&mut XzDecoder::new_multi_decoder(reader), writer),
+    };
+    result.map_err(|e| anyhow::anyhow!("Failed to decompress as {}: {}", algorithm, e))?;
+
+    writer.flush()?;
+    Ok(algorithm)
+}
+
+/// Compress and base64 encode in one go, for pasting the result into configs
+pub fn process_compress_base64(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    algorithm: CompressAlgorithm,
+    level: Option<u32>,
+) -> Result<()> {
+    let mut compressed = Vec::new();
+    process_compress(reader, &mut compressed, algorithm, level)?;
+    process_base64_encode(&mut &compressed[..], writer, Base64Format::Standard, 0)
+}
+
+/// Base64 decode (any variant) and then decompress
+pub fn process_decompress_base64(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    algorithm: Option<CompressAlgorithm>,
+) -> Result<CompressAlgorithm> {
+    let mut compressed = Vec::new();
+    process_base64_decode(reader, &mut compressed, Base64Format::Auto)?;
+    process_decompress(&mut &compressed[..], writer, algorithm)
+}
+
+fn detect_algorithm(magic: &[u8]) -> Option<CompressAlgorithm> {
+    Some(match magic {
+        [0x1f, 0x8b, ..] => CompressAlgorithm::Gzip,
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => CompressAlgorithm::Zstd,
+        [0xfd, b'7', b'z', b'X', b'Z', 0x00] => CompressAlgorithm::Xz,
+        // zlib: deflate method with a header checksum that is a multiple of 31
+        [cmf, flg, ..]
+            if cmf & 0x0f == 8 && u16::from_be_bytes([*cmf, *flg]).is_multiple_of(31) =>
+        {
+            CompressAlgorithm::Deflate
+        }
+        _ => return None,
+    })
+}
+
+fn read_magic(reader: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALGORITHMS: [CompressAlgorithm; 5] = [
+        CompressAlgorithm::Gzip,
+        CompressAlgorithm::Deflate,
+        CompressAlgorithm::Zstd,
+        CompressAlgorithm::Brotli,
+        CompressAlgorithm::Xz,
+    ];
+
+    #[test]
+    fn test_compress_roundtrip_detects_algorithm() -> Result<()> {
+        let data = std::fs::read("Cargo.toml")?;
+        for algorithm in ALGORITHMS {
+            let mut compressed = Vec::new();
+            process_compress(&mut &data[..], &mut compressed, algorithm, None)?;
+            assert!(compressed.len() < data.len(), "{}", algorithm);
+
+            // brotli has no magic bytes to detect
+            let given = matches!(algorithm, CompressAlgorithm::Brotli).then_some(algorithm);
+            let mut decompressed = Vec::new();
+            let detected = process_decompress(&mut &compressed[..], &mut decompressed, given)?;
+            assert_eq!(detected.to_string(), algorithm.to_string());
+            assert_eq!(decompressed, data);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress_unknown_format() {
+        let mut decompressed = Vec::new();
+        let err =
+            process_decompress(&mut &b"not compressed"[..], &mut decompressed, None).unwrap_err();
+        assert!(err.to_string().starts_with("Unknown format"));
+        assert!(decompressed.is_empty());
+    }
+
+    #[test]
+    fn test_compress_base64_chain() -> Result<()> {
+        let data = b"hello hello hello hello";
+        let mut encoded = Vec::new();
+        process_compress_base64(
+            &mut &data[..],
+            &mut encoded,
+            CompressAlgorithm::Zstd,
+            Some(19),
+        )?;
+
+        let mut decoded = Vec::new();
+        process_decompress_base64(&mut &encoded[..], &mut decoded, None)?;
+        assert_eq!(decoded, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_level_range() {
+        let result = process_compress(
+            &mut &b""[..],
+            &mut Vec::new(),
+            CompressAlgorithm::Gzip,
+            Some(10),
+        );
+        assert!(result.is_err());
+    }
+}
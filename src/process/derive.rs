@@ -0,0 +1,256 @@
+use crate::{
+    command::{DeriveCostOpts, KdfAlgorithm, TextSignFormat},
+    process::{
+        key_id, load_master_secret, Ed25519Signer, KeyEnvelope, BLAKE3_KEY, CHACHA20_KEY, HMAC_KEY,
+    },
+};
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, AssociatedData, Params, ParamsBuilder, Version};
+use ed25519_dalek::SigningKey;
+use hkdf::Hkdf;
+use sha2::{Sha256, Sha512};
+
+const DEFAULT_KEY_LEN: usize = 32;
+const ARGON2_CONTEXT_KEY: &str = "rcli derive 2024 argon2id context";
+
+/// Derive a key file (or key pair) of `format` from the master key at `key`; the same inputs
+/// always give the same key
+pub fn process_text_derive(
+    key: &str,
+    kdf: KdfAlgorithm,
+    format: TextSignFormat,
+    context: &str,
+    salt: &[u8],
+    length: Option<usize>,
+    cost: &DeriveCostOpts,
+) -> Result<Vec<Vec<u8>>> {
+    if context.is_empty() {
+        anyhow::bail!("Context must not be empty");
+    }
+    let (algorithm, len) = match format {
+        TextSignFormat::Blake3 => (Some(BLAKE3_KEY), DEFAULT_KEY_LEN),
+        TextSignFormat::Chacha => (Some(CHACHA20_KEY), DEFAULT_KEY_LEN),
+        TextSignFormat::HmacSha256
+        | TextSignFormat::HmacSha384
+        | TextSignFormat::HmacSha512
+        | TextSignFormat::HmacSha3_256 => (Some(HMAC_KEY), length.unwrap_or(DEFAULT_KEY_LEN)),
+        // the derived bytes are the ed25519 seed
+        TextSignFormat::Ed25519 | TextSignFormat::Ed25519ph => (None, DEFAULT_KEY_LEN),
+        format => anyhow::bail!(
+            "Cannot derive {} keys, use blake3, chacha, hmac-* or ed25519",
+            format
+        ),
+    };
+    if length.is_some_and(|length| length != len) {
+        anyhow::bail!("{} keys are always {} bytes", format, len);
+    }
+    if len == 0 {
+        anyhow::bail!("Key length must not be zero");
+    }
+
+    let master = load_master_secret(key)?;
+    let derived = derive_bytes(&master, kdf, context.as_bytes(), salt, len, cost)?;
+    match algorithm {
+        Some(algorithm) => Ok(vec![KeyEnvelope::new(
+            algorithm,
+            &key_id(&derived),
+            &derived,
+        )
+        .to_vec()?]),
+        None => {
+            let seed = derived.try_into().expect("ed25519 seeds are 32 bytes");
+            Ed25519Signer::key_files(&SigningKey::from_bytes(&seed))
+        }
+    }
+}
+
+fn derive_bytes(
+    master: &[u8],
+    kdf: KdfAlgorithm,
+    context: &[u8],
+    salt: &[u8],
+    len: usize,
+    cost: &DeriveCostOpts,
+) -> Result<Vec<u8>> {
+    let has_cost = cost.memory.is_some() || cost.iterations.is_some() || cost.parallelism.is_some();
+    if has_cost && !matches!(kdf, KdfAlgorithm::Argon2id) {
+        anyhow::bail!("--memory, --iterations and --parallelism only apply to argon2id");
+    }
+    let mut okm = vec![0u8; len];
+    let salt = (!salt.is_empty()).then_some(salt);
+    match kdf {
+        KdfAlgorithm::HkdfSha256 => Hkdf::<Sha256>::new(salt, master)
+            .expand(context, &mut okm)
+            .map_err(|_| anyhow!("HKDF-SHA256 keys are at most {} bytes", 255 * 32))?,
+        KdfAlgorithm::HkdfSha512 => Hkdf::<Sha512>::new(salt, master)
+            .expand(context, &mut okm)
+            .map_err(|_| anyhow!("HKDF-SHA512 keys are at most {} bytes", 255 * 64))?,
+        KdfAlgorithm::Blake3 => {
+            if salt.is_some() {
+                anyhow::bail!("BLAKE3 derive_key takes no salt, put it in the context");
+            }
+            let context = std::str::from_utf8(context)?;
+            blake3::Hasher::new_derive_key(context)
+                .update(master)
+                .finalize_xof()
+                .fill(&mut okm);
+        }
+        KdfAlgorithm::Argon2id => {
+            let salt = salt.ok_or_else(|| anyhow!("Argon2id needs a --salt"))?;
+            if salt.len() < argon2::MIN_SALT_LEN {
+                anyhow::bail!(
+                    "Argon2id salt must be at least {} bytes",
+                    argon2::MIN_SALT_LEN
+                );
+            }
+            // Argon2 associated data is capped at 32 bytes, so bind a hash of the context
+            let context = blake3::derive_key(ARGON2_CONTEXT_KEY, context);
+            let data = AssociatedData::new(&context).expect("BLAKE3 output fits Argon2 data");
+            let params = ParamsBuilder::new()
+                .m_cost(cost.memory.unwrap_or(Params::DEFAULT_M_COST))
+                .t_cost(cost.iterations.unwrap_or(Params::DEFAULT_T_COST))
+                .p_cost(cost.parallelism.unwrap_or(Params::DEFAULT_P_COST))
+                .data(data)
+                .output_len(len)
+                .build()
+                .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(master, salt, &mut okm)
+                .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+        }
+    }
+    Ok(okm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        process::{Blake3, Ed25519Verifier, TextSign, TextVerify},
+        KeyLoader,
+    };
+
+    fn cheap_cost() -> DeriveCostOpts {
+        DeriveCostOpts {
+            memory: Some(64),
+            iterations: Some(1),
+            parallelism: Some(1),
+        }
+    }
+
+    #[test]
+    fn test_hkdf_rfc5869_vector() -> Result<()> {
+        // RFC 5869 A.1
+        let hex = |s: &str| data_encoding::HEXLOWER.decode(s.as_bytes()).unwrap();
+        let okm = derive_bytes(
+            &[0x0b; 22],
+            KdfAlgorithm::HkdfSha256,
+            &hex("f0f1f2f3f4f5f6f7f8f9"),
+            &hex("000102030405060708090a0b0c"),
+            42,
+            &DeriveCostOpts::default(),
+        )?;
+        assert_eq!(
+            okm,
+            hex(concat!(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf",
+                "34007208d5b887185865"
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_kdfs() -> Result<()> {
+        let master = [7u8; 32];
+        let okm = derive_bytes(
+            &master,
+            KdfAlgorithm::Blake3,
+            b"ctx",
+            b"",
+            32,
+            &DeriveCostOpts::default(),
+        )?;
+        assert_eq!(okm, blake3::derive_key("ctx", &master));
+        assert!(derive_bytes(
+            &master,
+            KdfAlgorithm::Blake3,
+            b"ctx",
+            b"salt",
+            32,
+            &DeriveCostOpts::default()
+        )
+        .is_err());
+        // cost flags only mean something to argon2id
+        assert!(derive_bytes(
+            &master,
+            KdfAlgorithm::Blake3,
+            b"ctx",
+            b"",
+            32,
+            &cheap_cost()
+        )
+        .is_err());
+
+        let argon2 = |context: &[u8], salt: &[u8]| {
+            derive_bytes(
+                &master,
+                KdfAlgorithm::Argon2id,
+                context,
+                salt,
+                32,
+                &cheap_cost(),
+            )
+        };
+        assert_eq!(argon2(b"a", b"saltsalt")?, argon2(b"a", b"saltsalt")?);
+        assert_ne!(argon2(b"a", b"saltsalt")?, argon2(b"b", b"saltsalt")?);
+        assert!(argon2(b"a", b"short").is_err());
+        assert!(argon2(b"a", b"").is_err());
+
+        // contexts longer than Argon2's 32-byte associated data still bind in full
+        let long = [b'x'; 64];
+        let longer = [&long[..], b"y"].concat();
+        assert_ne!(argon2(&long, b"saltsalt")?, argon2(&longer, b"saltsalt")?);
+        assert_eq!(
+            data_encoding::HEXLOWER.encode(&argon2(b"a", b"saltsalt")?),
+            "28d4df512b10c2a94e41046d37df59f439bd96f79734440534ce3196f5eb246f"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_key_files() -> Result<()> {
+        let derive = |format, context| {
+            process_text_derive(
+                "fixtures/blake3.key",
+                KdfAlgorithm::HkdfSha512,
+                format,
+                context,
+                b"",
+                None,
+                &DeriveCostOpts::default(),
+            )
+        };
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("derived.key");
+        std::fs::write(&path, &derive(TextSignFormat::Blake3, "service a")?[0])?;
+        let signer = Blake3::load(&path)?;
+        let sig = signer.sign(&mut &b"hello"[..])?;
+
+        // same master and context give the same key, another context does not
+        std::fs::write(&path, &derive(TextSignFormat::Blake3, "service a")?[0])?;
+        assert!(Blake3::load(&path)?.verify(&mut &b"hello"[..], &sig)?);
+        std::fs::write(&path, &derive(TextSignFormat::Blake3, "service b")?[0])?;
+        assert!(!Blake3::load(&path)?.verify(&mut &b"hello"[..], &sig)?);
+
+        let keys = derive(TextSignFormat::Ed25519, "signing")?;
+        let (sk, pk) = (dir.path().join("derived.sk"), dir.path().join("derived.pk"));
+        std::fs::write(&sk, &keys[0])?;
+        std::fs::write(&pk, &keys[1])?;
+        let sig = Ed25519Signer::load(&sk)?.sign(&mut &b"hello"[..])?;
+        assert!(Ed25519Verifier::load(&pk)?.verify(&mut &b"hello"[..], &sig)?);
+
+        assert!(derive(TextSignFormat::P256, "signing").is_err());
+        Ok(())
+    }
+}
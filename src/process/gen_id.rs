@@ -0,0 +1,233 @@
+use crate::command::IdKind;
+use anyhow::Result;
+use base64::prelude::*;
+use chrono::{DateTime, Utc};
+use data_encoding::{BASE32_NOPAD, HEXLOWER};
+use rand::{rngs::OsRng, RngCore};
+use uuid::{Builder, Uuid};
+
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const SNOWFLAKE_MAX_WORKER: u16 = 1 << 10;
+const SNOWFLAKE_MAX_SEQUENCE: u64 = 1 << 12;
+
+pub fn process_gen_id(
+    kind: IdKind,
+    count: usize,
+    size: Option<usize>,
+    alphabet: &str,
+    worker: u16,
+    epoch: u64,
+) -> Result<Vec<String>> {
+    if matches!(kind, IdKind::Snowflake) && worker >= SNOWFLAKE_MAX_WORKER {
+        anyhow::bail!("Worker id must be below {}", SNOWFLAKE_MAX_WORKER);
+    }
+    if matches!(kind, IdKind::Snowflake) && epoch > now_millis() {
+        anyhow::bail!("Epoch {} is in the future", epoch);
+    }
+    let alphabet: Vec<char> = alphabet.chars().collect();
+    if matches!(kind, IdKind::Nanoid) && (alphabet.is_empty() || alphabet.len() > 256) {
+        anyhow::bail!("Alphabet must contain between 1 and 256 characters");
+    }
+
+    let mut ids = Vec::with_capacity(count);
+    // no millisecond has been used yet, so the first id gets sequence 0
+    let (mut last, mut sequence) = (u64::MAX, 0);
+    while ids.len() < count {
+        let id = match kind {
+            IdKind::Uuid4 => Builder::from_random_bytes(random_bytes())
+                .into_uuid()
+                .to_string(),
+            IdKind::Uuid7 => Builder::from_unix_timestamp_millis(now_millis(), &random_bytes())
+                .into_uuid()
+                .to_string(),
+            IdKind::Ulid => ulid(now_millis()),
+            IdKind::Nanoid => nanoid(&alphabet, size.unwrap_or(21)),
+            IdKind::Snowflake => {
+                let now = now_millis().saturating_sub(epoch);
+                if now == last {
+                    if sequence + 1 == SNOWFLAKE_MAX_SEQUENCE {
+                        // sequence exhausted for this millisecond, wait for the next one
+                        continue;
+                    }
+                    sequence += 1;
+                } else {
+                    last = now;
+                    sequence = 0;
+                }
+                ((now << 22) | ((worker as u64) << 12) | sequence).to_string()
+            }
+            IdKind::Hex => HEXLOWER.encode(&random_vec(size.unwrap_or(16))),
+            IdKind::Base64 => BASE64_URL_SAFE_NO_PAD.encode(random_vec(size.unwrap_or(16))),
+            IdKind::Base32 => BASE32_NOPAD.encode(&random_vec(size.unwrap_or(16))),
+        };
+        ids.push(id);
+    }
+
+    Ok(ids)
+}
+
+/// Decode the timestamp (and other fields) embedded in a UUID, ULID or Snowflake id
+pub fn process_inspect_id(id: &str, epoch: u64) -> Result<Vec<(&'static str, String)>> {
+    let id = id.trim();
+    let mut fields = Vec::new();
+
+    if let Ok(uuid) = Uuid::parse_str(id) {
+        let version = uuid.get_version_num();
+        fields.push(("kind", format!("uuid v{}", version)));
+        fields.push(("variant", format!("{:?}", uuid.get_variant())));
+        if version == 7 {
+            let bytes = uuid.as_bytes();
+            let mut millis = [0u8; 8];
+            millis[2..].copy_from_slice(&bytes[..6]);
+            fields.push(("timestamp", format_millis(u64::from_be_bytes(millis))?));
+        }
+    } else if id.len() == 26 {
+        let value = decode_ulid(id)?;
+        fields.push(("kind", "ulid".to_string()));
+        fields.push(("timestamp", format_millis((value >> 80) as u64)?));
+        fields.push(("random", format!("{:020x}", value & ((1u128 << 80) - 1))));
+    } else if let Ok(value) = id.parse::<u64>() {
+        let millis = (value >> 22)
+            .checked_add(epoch)
+            .ok_or_else(|| anyhow::anyhow!("Snowflake timestamp overflows with epoch {}", epoch))?;
+        fields.push(("kind", "snowflake".to_string()));
+        fields.push(("timestamp", format_millis(millis)?));
+        fields.push(("worker", ((value >> 12) & 0x3ff).to_string()));
+        fields.push(("sequence", (value & 0xfff).to_string()));
+    } else {
+        anyhow::bail!("Unrecognized id: {}", id);
+    }
+
+    Ok(fields)
+}
+
+fn now_millis() -> u64 {
+    Utc::now().timestamp_millis() as u64
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn random_vec(size: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; size];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn nanoid(alphabet: &[char], size: usize) -> String {
+    // mask + rejection sampling avoids the modulo bias
+    let mask = (alphabet.len().next_power_of_two() - 1) as u8;
+    let mut id = String::with_capacity(size);
+    let mut len = 0;
+    while len < size {
+        for byte in random_vec(size) {
+            let idx = (byte & mask) as usize;
+            if idx < alphabet.len() {
+                id.push(alphabet[idx]);
+                len += 1;
+                if len == size {
+                    break;
+                }
+            }
+        }
+    }
+    id
+}
+
+fn ulid(millis: u64) -> String {
+    let random = u128::from_be_bytes({
+        let mut bytes = [0u8; 16];
+        bytes[6..].copy_from_slice(&random_bytes::<10>());
+        bytes
+    });
+    let value = ((millis as u128) << 80) | random;
+    (0..26)
+        .rev()
+        .map(|i| CROCKFORD[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+fn decode_ulid(id: &str) -> Result<u128> {
+    let mut value = 0u128;
+    for (i, c) in id.chars().enumerate() {
+        let c = match c.to_ascii_uppercase() {
+            'I' | 'L' => '1',
+            'O' => '0',
+            c => c,
+        };
+        let digit = CROCKFORD
+            .iter()
+            .position(|&x| x as char == c)
+            .ok_or_else(|| anyhow::anyhow!("Invalid ULID character: {}", c))?;
+        if i == 0 && digit > 7 {
+            anyhow::bail!("ULID overflows 128 bits");
+        }
+        value = (value << 5) | digit as u128;
+    }
+    Ok(value)
+}
+
+fn format_millis(millis: u64) -> Result<String> {
+    let time = DateTime::from_timestamp_millis(millis as i64)
+        .ok_or_else(|| anyhow::anyhow!("Timestamp out of range"))?;
+    Ok(time.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NANOID_ALPHABET: &str =
+        "_-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+    #[test]
+    fn test_gen_id_formats() -> Result<()> {
+        let ids = process_gen_id(IdKind::Uuid4, 3, None, "", 0, 0)?;
+        assert_eq!(ids.len(), 3);
+        assert_eq!(Uuid::parse_str(&ids[0])?.get_version_num(), 4);
+
+        let ids = process_gen_id(IdKind::Hex, 1, Some(8), "", 0, 0)?;
+        assert_eq!(ids[0].len(), 16);
+
+        let ids = process_gen_id(IdKind::Nanoid, 5, Some(10), "abc", 0, 0)?;
+        assert!(ids
+            .iter()
+            .all(|id| id.len() == 10 && id.chars().all(|c| "abc".contains(c))));
+
+        let ids = process_gen_id(IdKind::Nanoid, 1, None, NANOID_ALPHABET, 0, 0)?;
+        assert_eq!(ids[0].len(), 21);
+
+        let ids = process_gen_id(IdKind::Snowflake, 100, None, "", 7, 0)?;
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), 100);
+        assert_eq!(ids[0].parse::<u64>()? & 0xfff, 0);
+
+        // a future epoch would freeze the timestamp and spin once the sequence runs out
+        assert!(process_gen_id(IdKind::Snowflake, 5000, None, "", 7, u64::MAX).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_timestamps() -> Result<()> {
+        let ulid = &process_gen_id(IdKind::Ulid, 1, None, "", 0, 0)?[0];
+        assert_eq!(ulid.len(), 26);
+        let fields = process_inspect_id(ulid, 0)?;
+        assert_eq!(fields[0].1, "ulid");
+
+        // example from the ULID spec: 01ARZ3NDEKTSV4RRFFQ69G5FAV -> 2016-07-30T23:54:10.259Z
+        let fields = process_inspect_id("01ARZ3NDEKTSV4RRFFQ69G5FAV", 0)?;
+        assert_eq!(fields[1].1, "2016-07-30T23:54:10.259+00:00");
+
+        // example from RFC 9562 appendix A.6
+        let fields = process_inspect_id("017F22E2-79B0-7CC3-98C4-DC0C0C07398F", 0)?;
+        assert_eq!(fields[0].1, "uuid v7");
+        assert_eq!(fields[2].1, "2022-02-22T19:22:22+00:00");
+
+        // a custom epoch that pushes the timestamp past u64 is an error, not a panic
+        assert!(process_inspect_id(&u64::MAX.to_string(), u64::MAX).is_err());
+        Ok(())
+    }
+}
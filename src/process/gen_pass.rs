@@ -1,4 +1,8 @@
+use crate::{command::DeriveKdf, process::load_master_secret, KeyLoader};
+use anyhow::Result;
+use argon2::Argon2;
 use rand::seq::SliceRandom;
+use std::path::Path;
 
 const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
 const LOWER: &[u8] = b"abcdefghjkmnopqrstuvwxyz";
@@ -44,3 +48,210 @@ pub fn process_gen_pass(
 
     Ok(password)
 }
+
+pub struct MasterSecret {
+    secret: Vec<u8>,
+}
+
+impl MasterSecret {
+    fn new(secret: Vec<u8>) -> Result<Self> {
+        if secret.is_empty() {
+            anyhow::bail!("Master secret must not be empty");
+        }
+        Ok(Self { secret })
+    }
+
+    pub fn from_env(name: &str) -> Result<Self> {
+        let secret = std::env::var(name)
+            .map_err(|_| anyhow::anyhow!("Environment variable {} is not set", name))?;
+        Self::new(secret.into_bytes())
+    }
+
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    pub fn prompt() -> Result<Self> {
+        let secret = rpassword::prompt_password("Master secret: ")?;
+        Self::new(secret.into_bytes())
+    }
+}
+
+impl KeyLoader for MasterSecret {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        // same rules as `text derive`, so one master file gives one secret everywhere
+        Self::new(load_master_secret(path)?)
+    }
+}
+
+const DERIVE_CONTEXT: &str = "rcli 2024-05 genpass derive master key";
+const STREAM_CONTEXT: &[u8] = b"rcli genpass derive stream";
+
+/// Derive a 32 bytes seed for the given site/login/counter from the master secret
+pub fn derive_pass_seed(
+    master: &[u8],
+    site: &str,
+    login: &str,
+    counter: u32,
+    kdf: DeriveKdf,
+) -> anyhow::Result<[u8; 32]> {
+    let mut salt = Vec::new();
+    for field in [site.as_bytes(), login.as_bytes(), &counter.to_le_bytes()] {
+        salt.extend_from_slice(&(field.len() as u64).to_le_bytes());
+        salt.extend_from_slice(field);
+    }
+
+    let seed = match kdf {
+        DeriveKdf::Blake3 => {
+            let key = blake3::derive_key(DERIVE_CONTEXT, master);
+            *blake3::keyed_hash(&key, &salt).as_bytes()
+        }
+        DeriveKdf::Argon2id => {
+            let salt = blake3::hash(&salt);
+            let mut seed = [0u8; 32];
+            Argon2::default()
+                .hash_password_into(master, salt.as_bytes(), &mut seed)
+                .map_err(|e| anyhow::anyhow!("Argon2 derivation failed: {}", e))?;
+            seed
+        }
+    };
+
+    Ok(seed)
+}
+
+/// Deterministically map the seed onto the enabled alphabets
+pub fn process_derive_pass(
+    seed: &[u8; 32],
+    length: u8,
+    upper: bool,
+    lower: bool,
+    number: bool,
+    symbol: bool,
+) -> anyhow::Result<String> {
+    let mut stream = blake3::Hasher::new_keyed(seed)
+        .update(STREAM_CONTEXT)
+        .finalize_xof();
+    let mut pick = |n: usize| -> usize {
+        // rejection sampling to keep the distribution uniform
+        let zone = u32::MAX - u32::MAX % n as u32;
+        loop {
+            let mut buf = [0u8; 4];
+            stream.fill(&mut buf);
+            let v = u32::from_le_bytes(buf);
+            if v < zone {
+                return (v % n as u32) as usize;
+            }
+        }
+    };
+
+    let mut pass = Vec::new();
+    let mut chars = Vec::new();
+
+    for (enabled, set) in [
+        (upper, UPPER),
+        (lower, LOWER),
+        (number, NUMBER),
+        (symbol, SYMBOL),
+    ] {
+        if enabled {
+            chars.extend_from_slice(set);
+            pass.push(set[pick(set.len())]);
+        }
+    }
+
+    if chars.is_empty() {
+        anyhow::bail!("At least one character class must be enabled");
+    }
+    if (length as usize) < pass.len() {
+        anyhow::bail!("Length must be at least {}", pass.len());
+    }
+
+    for _ in 0..(length as usize - pass.len()) {
+        pass.push(chars[pick(chars.len())]);
+    }
+
+    // Fisher-Yates shuffle driven by the derived stream
+    for i in (1..pass.len()).rev() {
+        let j = pick(i + 1);
+        pass.swap(i, j);
+    }
+
+    let password = String::from_utf8(pass)?;
+
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_pass_is_deterministic() -> anyhow::Result<()> {
+        let seed = derive_pass_seed(b"master", "example.com", "alice", 1, DeriveKdf::Blake3)?;
+        let p1 = process_derive_pass(&seed, 16, true, true, true, true)?;
+        let p2 = process_derive_pass(&seed, 16, true, true, true, true)?;
+        assert_eq!(p1, p2);
+        assert_eq!(p1.len(), 16);
+
+        let seed = derive_pass_seed(b"master", "example.com", "alice", 2, DeriveKdf::Blake3)?;
+        let p3 = process_derive_pass(&seed, 16, true, true, true, true)?;
+        assert_ne!(p1, p3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_pass_vectors() -> anyhow::Result<()> {
+        // stateless: these must never change, or every derived password changes with them
+        let seed = derive_pass_seed(b"master", "example.com", "alice", 1, DeriveKdf::Blake3)?;
+        assert_eq!(
+            process_derive_pass(&seed, 16, true, true, true, true)?,
+            "ZHsshe2b&b^Pj3N#"
+        );
+        let seed = derive_pass_seed(b"master", "example.com", "alice", 1, DeriveKdf::Argon2id)?;
+        assert_eq!(
+            process_derive_pass(&seed, 20, true, true, true, false)?,
+            "mwYddE2dG73TazM2Uox2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_master_secret_file_formats() -> anyhow::Result<()> {
+        use crate::process::{key_id, KeyEnvelope, BLAKE3_KEY};
+
+        let dir = tempfile::tempdir()?;
+        let (raw, envelope) = (dir.path().join("master"), dir.path().join("master.key"));
+        std::fs::write(&raw, b"master\n")?;
+        let key = KeyEnvelope::new(BLAKE3_KEY, &key_id(b"master"), b"master").to_vec()?;
+        std::fs::write(&envelope, key)?;
+
+        let derive = |path: &Path| -> anyhow::Result<String> {
+            let master = MasterSecret::load(path)?;
+            let seed = derive_pass_seed(
+                master.secret(),
+                "example.com",
+                "alice",
+                1,
+                DeriveKdf::Blake3,
+            )?;
+            process_derive_pass(&seed, 16, true, true, true, true)
+        };
+        assert_eq!(derive(&raw)?, "ZHsshe2b&b^Pj3N#");
+        assert_eq!(derive(&envelope)?, "ZHsshe2b&b^Pj3N#");
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_pass_honours_classes() -> anyhow::Result<()> {
+        let seed = derive_pass_seed(b"master", "example.com", "bob", 1, DeriveKdf::Argon2id)?;
+        let pass = process_derive_pass(&seed, 4, true, true, true, true)?;
+        for set in [UPPER, LOWER, NUMBER, SYMBOL] {
+            assert!(pass.bytes().any(|c| set.contains(&c)));
+        }
+
+        let pass = process_derive_pass(&seed, 12, false, false, true, false)?;
+        assert!(pass.bytes().all(|c| NUMBER.contains(&c)));
+        assert!(process_derive_pass(&seed, 2, true, true, true, true).is_err());
+        Ok(())
+    }
+}
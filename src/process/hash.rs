@@ -0,0 +1,324 @@
+use crate::{command::DigestAlgorithm, get_reader};
+use anyhow::Result;
+use blake2::Blake2b512;
+use rayon::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha512};
+use sha3::{Sha3_256, Sha3_512};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+use walkdir::WalkDir;
+use xxhash_rust::xxh3::Xxh3;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct FileDigest {
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HashReport {
+    pub algorithm: String,
+    pub files: Vec<FileDigest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+    Missing(String),
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Sha3_256(Sha3_256),
+    Sha3_512(Sha3_512),
+    Blake2b(Blake2b512),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3(Box<Xxh3>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    fn new(algorithm: DigestAlgorithm) -> Self {
+        match algorithm {
+            DigestAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            DigestAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            DigestAlgorithm::Sha3_256 => Hasher::Sha3_256(Sha3_256::new()),
+            DigestAlgorithm::Sha3_512 => Hasher::Sha3_512(Sha3_512::new()),
+            DigestAlgorithm::Blake2b => Hasher::Blake2b(Blake2b512::new()),
+            DigestAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            DigestAlgorithm::Xxh3 => Hasher::Xxh3(Box::new(Xxh3::new())),
+            DigestAlgorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Sha3_256(h) => h.update(data),
+            Hasher::Sha3_512(h) => h.update(data),
+            Hasher::Blake2b(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Xxh3(h) => h.update(data),
+            Hasher::Crc32(h) => h.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+            Hasher::Sha3_256(h) => h.finalize().to_vec(),
+            Hasher::Sha3_512(h) => h.finalize().to_vec(),
+            Hasher::Blake2b(h) => h.finalize().to_vec(),
+            Hasher::Blake3(h) => h.finalize().as_bytes().to_vec(),
+            Hasher::Xxh3(h) => h.digest().to_be_bytes().to_vec(),
+            Hasher::Crc32(h) => h.finalize().to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// Feed `reader` to `update` in fixed-size chunks, returning the byte count
+pub fn for_each_chunk(reader: &mut dyn Read, mut update: impl FnMut(&[u8])) -> io::Result<u64> {
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    let mut size = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(size),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        update(&buf[..n]);
+        size += n as u64;
+    }
+}
+
+/// Hash everything `reader` yields, returning the lowercase hex digest and the byte count
+pub fn hash_reader(reader: &mut dyn Read, algorithm: DigestAlgorithm) -> Result<(String, u64)> {
+    let mut hasher = Hasher::new(algorithm);
+    let size = for_each_chunk(reader, |chunk| hasher.update(chunk))?;
+    Ok((data_encoding::HEXLOWER.encode(&hasher.finalize()), size))
+}
+
+/// Hash files, walking directories recursively; files are hashed in parallel but reported in order
+pub fn process_text_hash(paths: &[String], algorithm: DigestAlgorithm) -> Result<HashReport> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path != "-" && Path::new(path).is_dir() {
+            files.extend(walk_files(path, false)?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    Ok(HashReport {
+        algorithm: algorithm.to_string(),
+        files: hash_files(files, algorithm)?,
+    })
+}
+
+/// Every regular file under `dir`, sorted by path. Symbolic links are skipped, or are an error
+/// when `reject_symlinks` is set
+pub fn walk_files(dir: impl AsRef<Path>, reject_symlinks: bool) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if reject_symlinks && entry.path_is_symlink() {
+            anyhow::bail!(
+                "{}: symbolic links are not followed",
+                entry.path().display()
+            );
+        }
+        if entry.file_type().is_file() {
+            files.push(entry.path().to_string_lossy().into_owned());
+        }
+    }
+    Ok(files)
+}
+
+/// Hash the files in parallel, keeping their order
+pub fn hash_files(files: Vec<String>, algorithm: DigestAlgorithm) -> Result<Vec<FileDigest>> {
+    files
+        .into_par_iter()
+        .map(|path| {
+            let (hash, size) = hash_reader(&mut get_reader(&path)?, algorithm)
+                .map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+            Ok(FileDigest { path, size, hash })
+        })
+        .collect()
+}
+
+/// Verify a `sha256sum`/`b3sum` style checksum file (`HASH  PATH` or `HASH *PATH` per line)
+pub fn process_text_hash_check(
+    checksums: &str,
+    algorithm: DigestAlgorithm,
+) -> Result<Vec<(String, CheckStatus)>> {
+    let mut entries = Vec::new();
+    for (n, line) in BufReader::new(get_reader(checksums)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_checksum_line(&line) {
+            Some(entry) => entries.push(entry),
+            None => eprintln!("{}: line {} is improperly formatted", checksums, n + 1),
+        }
+    }
+
+    let results = entries
+        .into_par_iter()
+        .map(|(expected, path)| {
+            let status = match File::open(&path) {
+                Ok(mut file) => match hash_reader(&mut file, algorithm) {
+                    Ok((hash, _)) if hash == expected => CheckStatus::Ok,
+                    Ok(_) => CheckStatus::Failed,
+                    Err(e) => CheckStatus::Missing(e.to_string()),
+                },
+                Err(e) => CheckStatus::Missing(e.to_string()),
+            };
+            (path, status)
+        })
+        .collect();
+    Ok(results)
+}
+
+/// Split a `HASH  PATH` line; a second space marks text mode and '*' binary mode. A leading
+/// '\\' marks an escaped path, as written by `format_checksum_line`
+pub fn parse_checksum_line(line: &str) -> Option<(String, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (hash, path) = line.split_once(' ')?;
+    let path = path.strip_prefix(' ').or_else(|| path.strip_prefix('*'))?;
+    let path = if escaped {
+        unescape_path(path)?
+    } else {
+        path.to_string()
+    };
+    Some((hash.to_ascii_lowercase(), path))
+}
+
+/// Join a hash and path the way `b3sum`/`sha256sum` do, escaping paths that hold '\\' or
+/// line breaks so every entry stays on one line
+pub fn format_checksum_line(hash: &str, path: &str) -> String {
+    if !path.contains(['\\', '\n', '\r']) {
+        return format!("{}  {}", hash, path);
+    }
+    let path = path
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    format!("\\{}  {}", hash, path)
+}
+
+fn unescape_path(path: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+impl fmt::Display for FileDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_checksum_line(&self.hash, &self.path))
+    }
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Ok => write!(f, "OK"),
+            CheckStatus::Failed => write!(f, "FAILED"),
+            CheckStatus::Missing(e) => write!(f, "FAILED open or read ({})", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_known_vectors() -> Result<()> {
+        for (algorithm, expected) in [
+            (
+                DigestAlgorithm::Sha256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                DigestAlgorithm::Sha3_256,
+                "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            ),
+            (
+                DigestAlgorithm::Blake3,
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+            (DigestAlgorithm::Crc32, "352441c2"),
+            (DigestAlgorithm::Xxh3, "78af5f94892f3950"),
+        ] {
+            let (hash, size) = hash_reader(&mut &b"abc"[..], algorithm)?;
+            assert_eq!(hash, expected, "{}", algorithm);
+            assert_eq!(size, 3);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_directory_and_check() -> Result<()> {
+        let report = process_text_hash(&["fixtures/base64".to_string()], DigestAlgorithm::Blake3)?;
+        assert_eq!(report.files.len(), 3);
+        assert!(report.files.windows(2).all(|w| w[0].path < w[1].path));
+
+        let dir = tempfile::tempdir()?;
+        let checksums = dir.path().join("checksums.txt");
+        let mut lines: Vec<String> = report.files.iter().map(|f| f.to_string()).collect();
+        lines.push(format!("{}  fixtures/blake3.key", "0".repeat(64)));
+        std::fs::write(&checksums, lines.join("\n"))?;
+
+        let results =
+            process_text_hash_check(checksums.to_str().unwrap(), DigestAlgorithm::Blake3)?;
+        assert_eq!(results.len(), 4);
+        assert!(results[..3].iter().all(|(_, s)| *s == CheckStatus::Ok));
+        assert_eq!(results[3].1, CheckStatus::Failed);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hash_directory_skips_symlinks() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), b"alpha")?;
+        std::os::unix::fs::symlink("/etc/passwd", dir.path().join("link"))?;
+
+        let path = dir.path().to_str().unwrap().to_string();
+        let report = process_text_hash(&[path], DigestAlgorithm::Blake3)?;
+        assert_eq!(report.files.len(), 1);
+        assert!(report.files[0].path.ends_with("a.txt"));
+        Ok(())
+    }
+}
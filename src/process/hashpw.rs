@@ -0,0 +1,260 @@
+use crate::command::{HashAlgorithm, HashCostOpts};
+use anyhow::{anyhow, Result};
+use argon2::{
+    password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use pbkdf2::Pbkdf2;
+use rand::rngs::OsRng;
+use scrypt::Scrypt;
+use std::time::{Duration, Instant};
+
+/// Calibration stops here rather than picking an unbounded time cost from one timing sample
+const MAX_CALIBRATE_ITERATIONS: u32 = 12;
+
+pub fn process_hashpw_hash(
+    password: &str,
+    algorithm: HashAlgorithm,
+    cost: &HashCostOpts,
+) -> Result<String> {
+    check_cost_flags(algorithm, cost)?;
+    let password = password.as_bytes();
+    let salt = SaltString::generate(&mut OsRng);
+
+    let hash = match algorithm {
+        HashAlgorithm::Argon2id => {
+            let params = argon2_params(cost)?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password(password, &salt)?
+                .to_string()
+        }
+        HashAlgorithm::Bcrypt => {
+            let cost = cost.cost.unwrap_or(bcrypt::DEFAULT_COST);
+            bcrypt::hash(password, cost)?
+        }
+        HashAlgorithm::Scrypt => {
+            let params = scrypt::Params::new(
+                cost.log_n.unwrap_or(scrypt::Params::RECOMMENDED_LOG_N),
+                cost.block_size.unwrap_or(scrypt::Params::RECOMMENDED_R),
+                cost.parallelism.unwrap_or(scrypt::Params::RECOMMENDED_P),
+                scrypt::Params::RECOMMENDED_LEN,
+            )?;
+            Scrypt
+                .hash_password_customized(password, None, None, params, &salt)?
+                .to_string()
+        }
+        HashAlgorithm::Pbkdf2 => {
+            let params = pbkdf2::Params {
+                rounds: cost
+                    .iterations
+                    .unwrap_or(pbkdf2::Params::RECOMMENDED_ROUNDS as u32),
+                output_length: 32,
+            };
+            Pbkdf2
+                .hash_password_customized(password, None, None, params, &salt)?
+                .to_string()
+        }
+    };
+
+    Ok(hash)
+}
+
+pub fn process_hashpw_verify(password: &str, hash: &str) -> Result<bool> {
+    // bcrypt uses its own modular crypt format rather than PHC
+    if hash.starts_with("$2") {
+        return Ok(bcrypt::verify(password, hash)?);
+    }
+
+    let hash = PasswordHash::new(hash).map_err(|e| anyhow!("Invalid PHC string: {}", e))?;
+    let verifier: &dyn PasswordVerifier = match hash.algorithm.as_str() {
+        "argon2id" | "argon2i" | "argon2d" => &Argon2::default(),
+        "scrypt" => &Scrypt,
+        "pbkdf2" | "pbkdf2-sha256" | "pbkdf2-sha512" => &Pbkdf2,
+        algorithm => anyhow::bail!("Unsupported hash algorithm: {}", algorithm),
+    };
+    // only a mismatch is a "no", a hash we cannot check is an error
+    match verifier.verify_password(password.as_bytes(), &hash) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(anyhow!("Failed to verify {} hash: {}", hash.algorithm, e)),
+    }
+}
+
+/// Find the Argon2id iteration count that takes at least `target` for the given memory cost
+pub fn process_hashpw_calibrate(target: Duration, cost: &HashCostOpts) -> Result<Params> {
+    if cost.iterations.is_some() {
+        anyhow::bail!("--iterations is picked by --calibrate");
+    }
+    let memory = cost.memory.unwrap_or(Params::DEFAULT_M_COST);
+    let parallelism = cost.parallelism.unwrap_or(Params::DEFAULT_P_COST);
+    let salt = SaltString::generate(&mut OsRng);
+
+    let mut iterations = 1;
+    loop {
+        let params = Params::new(memory, iterations, parallelism, None)?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone());
+
+        let start = Instant::now();
+        argon2.hash_password(b"rcli calibration", &salt)?;
+        let elapsed = start.elapsed();
+
+        if elapsed >= target {
+            return Ok(params);
+        }
+        if iterations >= MAX_CALIBRATE_ITERATIONS {
+            anyhow::bail!(
+                "{} iterations take {:?}, short of {:?}; raise --memory instead",
+                iterations,
+                elapsed,
+                target
+            );
+        }
+
+        // scale linearly towards the target, but always make progress
+        let scaled = (iterations as f64 * target.as_secs_f64() / elapsed.as_secs_f64()) as u32;
+        iterations = scaled.clamp(iterations + 1, MAX_CALIBRATE_ITERATIONS);
+    }
+}
+
+/// Refuse cost flags the algorithm has no use for, rather than silently ignoring them
+fn check_cost_flags(algorithm: HashAlgorithm, cost: &HashCostOpts) -> Result<()> {
+    let (argon2, bcrypt, scrypt, pbkdf2) = (
+        matches!(algorithm, HashAlgorithm::Argon2id),
+        matches!(algorithm, HashAlgorithm::Bcrypt),
+        matches!(algorithm, HashAlgorithm::Scrypt),
+        matches!(algorithm, HashAlgorithm::Pbkdf2),
+    );
+    for (flag, given, applies) in [
+        ("--memory", cost.memory.is_some(), argon2),
+        ("--iterations", cost.iterations.is_some(), argon2 || pbkdf2),
+        (
+            "--parallelism",
+            cost.parallelism.is_some(),
+            argon2 || scrypt,
+        ),
+        ("--cost", cost.cost.is_some(), bcrypt),
+        ("--log-n", cost.log_n.is_some(), scrypt),
+        ("--block-size", cost.block_size.is_some(), scrypt),
+    ] {
+        if given && !applies {
+            anyhow::bail!("{} does not apply to {}", flag, algorithm);
+        }
+    }
+    Ok(())
+}
+
+fn argon2_params(cost: &HashCostOpts) -> Result<Params> {
+    Ok(Params::new(
+        cost.memory.unwrap_or(Params::DEFAULT_M_COST),
+        cost.iterations.unwrap_or(Params::DEFAULT_T_COST),
+        cost.parallelism.unwrap_or(Params::DEFAULT_P_COST),
+        None,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cheap_cost(algorithm: HashAlgorithm) -> HashCostOpts {
+        match algorithm {
+            HashAlgorithm::Argon2id => HashCostOpts {
+                memory: Some(1024),
+                iterations: Some(2),
+                parallelism: Some(1),
+                ..Default::default()
+            },
+            HashAlgorithm::Bcrypt => HashCostOpts {
+                cost: Some(4),
+                ..Default::default()
+            },
+            HashAlgorithm::Scrypt => HashCostOpts {
+                log_n: Some(4),
+                block_size: Some(8),
+                parallelism: Some(1),
+                ..Default::default()
+            },
+            HashAlgorithm::Pbkdf2 => HashCostOpts {
+                iterations: Some(2),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_hashpw_hash_verify() -> Result<()> {
+        for (algorithm, prefix) in [
+            (HashAlgorithm::Argon2id, "$argon2id$"),
+            (HashAlgorithm::Bcrypt, "$2b$04$"),
+            (HashAlgorithm::Scrypt, "$scrypt$"),
+            (HashAlgorithm::Pbkdf2, "$pbkdf2-sha256$"),
+        ] {
+            let hash = process_hashpw_hash("hunter2", algorithm, &cheap_cost(algorithm))?;
+            assert!(hash.starts_with(prefix), "{}", hash);
+            assert!(process_hashpw_verify("hunter2", &hash)?);
+            assert!(!process_hashpw_verify("hunter3", &hash)?);
+        }
+        // a hash that cannot be checked is an error rather than a mismatch
+        for hash in [
+            "$argon2id$v=19$m=8,t=1,p=1$c2FsdHNhbHQ$AAAA",
+            "$md5$c2FsdHNhbHQ$AAAA",
+            "$2b$04$notavalidbcrypthash",
+        ] {
+            assert!(process_hashpw_verify("hunter2", hash).is_err(), "{}", hash);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_hashpw_calibrate() -> Result<()> {
+        let cost = HashCostOpts {
+            memory: Some(1024),
+            ..Default::default()
+        };
+        let params = process_hashpw_calibrate(Duration::from_millis(5), &cost)?;
+        assert_eq!(params.m_cost(), 1024);
+        assert!(params.t_cost() >= 1);
+        assert!(params.t_cost() <= MAX_CALIBRATE_ITERATIONS);
+
+        // a target out of reach fails instead of picking an unbounded time cost
+        assert!(process_hashpw_calibrate(Duration::from_secs(3600), &cost).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hashpw_rejects_unused_cost_flags() {
+        for (algorithm, cost) in [
+            (
+                HashAlgorithm::Bcrypt,
+                HashCostOpts {
+                    parallelism: Some(2),
+                    ..Default::default()
+                },
+            ),
+            (
+                HashAlgorithm::Pbkdf2,
+                HashCostOpts {
+                    memory: Some(1024),
+                    ..Default::default()
+                },
+            ),
+            (
+                HashAlgorithm::Argon2id,
+                HashCostOpts {
+                    log_n: Some(4),
+                    ..Default::default()
+                },
+            ),
+            (
+                HashAlgorithm::Scrypt,
+                HashCostOpts {
+                    cost: Some(4),
+                    ..Default::default()
+                },
+            ),
+        ] {
+            let err = process_hashpw_hash("hunter2", algorithm, &cost).unwrap_err();
+            assert!(err.to_string().contains("does not apply"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::Result;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy)]
+pub struct HexdumpConfig {
+    pub columns: usize,
+    /// bytes per group, 0 puts the whole line in a single group
+    pub group: usize,
+    pub uppercase: bool,
+    pub color: bool,
+}
+
+/// Write an `xxd`-style dump of `reader`, starting at `skip` and stopping after `length` bytes
+pub fn process_hexdump(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    config: HexdumpConfig,
+    skip: u64,
+    length: Option<u64>,
+) -> Result<()> {
+    if config.columns == 0 {
+        anyhow::bail!("Columns must be at least 1");
+    }
+    let group = match config.group {
+        0 => config.columns,
+        g => g.min(config.columns),
+    };
+    // every group is followed by a space, plus one more before the ASCII gutter
+    let hex_width = config.columns * 2 + config.columns.div_ceil(group) + 1;
+
+    io::copy(&mut reader.take(skip), &mut io::sink())?;
+    let mut reader = reader.take(length.unwrap_or(u64::MAX));
+
+    let mut offset = skip;
+    let mut line = vec![0u8; config.columns];
+    loop {
+        let n = read_full(&mut reader, &mut line)?;
+        if n == 0 {
+            break;
+        }
+
+        let mut hex = String::new();
+        let mut ascii = String::new();
+        let mut width = 0;
+        for (i, &byte) in line[..n].iter().enumerate() {
+            let (color, c) = classify(byte);
+            if config.color {
+                hex.push_str(color);
+                ascii.push_str(color);
+            }
+            if config.uppercase {
+                hex.push_str(&format!("{:02X}", byte));
+            } else {
+                hex.push_str(&format!("{:02x}", byte));
+            }
+            ascii.push(c);
+            if config.color {
+                hex.push_str(RESET);
+                ascii.push_str(RESET);
+            }
+            width += 2;
+            if (i + 1).is_multiple_of(group) {
+                hex.push(' ');
+                width += 1;
+            }
+        }
+
+        writeln!(
+            writer,
+            "{:08x}: {}{}{}",
+            offset,
+            hex,
+            " ".repeat(hex_width - width),
+            ascii
+        )?;
+        offset += n as u64;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Rebuild the original bytes from an `xxd` dump (or plain hex lines)
+pub fn process_hexdump_reverse(reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+    let mut position = 0u64;
+    for (n, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        let hex = match line.split_once(':') {
+            Some((offset, rest)) => {
+                let offset = u64::from_str_radix(offset.trim(), 16)
+                    .map_err(|_| anyhow::anyhow!("Invalid offset on line {}", n + 1))?;
+                if offset < position {
+                    anyhow::bail!("Offset {:08x} on line {} goes backwards", offset, n + 1);
+                }
+                io::copy(&mut io::repeat(0).take(offset - position), writer)?;
+                position = offset;
+                // the ASCII gutter starts after two consecutive spaces
+                let rest = rest.strip_prefix(' ').unwrap_or(rest);
+                rest.split_once("  ").map_or(rest, |(hex, _)| hex)
+            }
+            None => line,
+        };
+
+        let digits: Vec<u8> = hex.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+        if !digits.len().is_multiple_of(2) {
+            anyhow::bail!("Odd number of hex digits on line {}", n + 1);
+        }
+        let bytes = data_encoding::HEXLOWER_PERMISSIVE
+            .decode(&digits)
+            .map_err(|e| anyhow::anyhow!("Invalid hex on line {}: {}", n + 1, e))?;
+        writer.write_all(&bytes)?;
+        position += bytes.len() as u64;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Colour and gutter character for a byte
+fn classify(byte: u8) -> (&'static str, char) {
+    match byte {
+        0 => ("\x1b[2m", '.'),
+        b' ' => ("\x1b[33m", ' '),
+        b'\t' | b'\n' | b'\r' => ("\x1b[33m", '.'),
+        0x21..=0x7e => ("\x1b[32m", byte as char),
+        _ => ("\x1b[31m", '.'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: HexdumpConfig = HexdumpConfig {
+        columns: 16,
+        group: 2,
+        uppercase: false,
+        color: false,
+    };
+
+    #[test]
+    fn test_hexdump_matches_xxd() -> Result<()> {
+        let data = b"Hello, world!\x00\x01\xff abcdefghijklmnop";
+        let mut dump = Vec::new();
+        process_hexdump(&mut &data[..], &mut dump, CONFIG, 0, None)?;
+        assert_eq!(
+            String::from_utf8(dump)?,
+            "00000000: 4865 6c6c 6f2c 2077 6f72 6c64 2100 01ff  Hello, world!...\n\
+             00000010: 2061 6263 6465 6667 6869 6a6b 6c6d 6e6f   abcdefghijklmno\n\
+             00000020: 70                                       p\n"
+        );
+
+        let config = HexdumpConfig {
+            columns: 8,
+            group: 0,
+            ..CONFIG
+        };
+        let mut dump = Vec::new();
+        process_hexdump(&mut &b"Hello"[..], &mut dump, config, 0, None)?;
+        assert_eq!(
+            String::from_utf8(dump)?,
+            "00000000: 48656c6c6f        Hello\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexdump_reverse_roundtrip() -> Result<()> {
+        let key = std::fs::read("fixtures/ed25519.sk")?;
+        for config in [
+            CONFIG,
+            HexdumpConfig {
+                columns: 7,
+                group: 3,
+                uppercase: true,
+                color: false,
+            },
+        ] {
+            let mut dump = Vec::new();
+            process_hexdump(&mut &key[..], &mut dump, config, 0, None)?;
+            let mut decoded = Vec::new();
+            process_hexdump_reverse(&mut &dump[..], &mut decoded)?;
+            assert_eq!(decoded, key);
+        }
+
+        let mut dump = Vec::new();
+        process_hexdump(&mut &key[..], &mut dump, CONFIG, 4, Some(8))?;
+        assert!(dump.starts_with(b"00000004: "));
+        let mut decoded = Vec::new();
+        process_hexdump_reverse(&mut &dump[..], &mut decoded)?;
+        assert_eq!(decoded[4..], key[4..12]);
+        Ok(())
+    }
+}
@@ -1,12 +1,15 @@
 use chrono::Utc;
 use core::fmt;
 use humantime::parse_duration;
-use std::{fs, path::Path};
+use std::path::Path;
 
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 
-use crate::KeyLoader;
+use crate::{
+    process::{load_key_material, HS256_KEY},
+    KeyLoader,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JwtClaims {
@@ -21,9 +24,7 @@ struct JwtKey {
 
 impl KeyLoader for JwtKey {
     fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let key = fs::read(path)?;
-        let key = &key[..32];
-        let key = key.try_into()?;
+        let key = load_key_material(path, HS256_KEY)?;
         Ok(Self { key })
     }
 }
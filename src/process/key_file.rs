@@ -0,0 +1,229 @@
+use anyhow::Result;
+use base64::prelude::*;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+pub const KEY_FILE_VERSION: u32 = 1;
+
+pub const BLAKE3_KEY: &str = "blake3";
+pub const ED25519_SECRET_KEY: &str = "ed25519-sk";
+pub const ED25519_PUBLIC_KEY: &str = "ed25519-pk";
+pub const CHACHA20_KEY: &str = "chacha20-poly1305";
+pub const HS256_KEY: &str = "hs256";
+pub const HMAC_KEY: &str = "hmac";
+
+const KEY_ID_CONTEXT: &str = "rcli 2024-05 key file id";
+
+/// Versioned JSON key file recording what the key is for and when it was made
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyEnvelope {
+    pub version: u32,
+    pub algorithm: String,
+    pub kid: String,
+    pub created: String,
+    /// base64url (no padding) key material
+    pub key: String,
+}
+
+impl KeyEnvelope {
+    pub fn new(algorithm: &str, kid: &str, key: &[u8]) -> Self {
+        Self {
+            version: KEY_FILE_VERSION,
+            algorithm: algorithm.to_string(),
+            kid: kid.to_string(),
+            created: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            key: BASE64_URL_SAFE_NO_PAD.encode(key),
+        }
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut data = serde_json::to_vec_pretty(self)?;
+        data.push(b'\n');
+        Ok(data)
+    }
+
+    pub fn material(&self) -> Result<Vec<u8>> {
+        if self.version != KEY_FILE_VERSION {
+            anyhow::bail!("Unsupported key file version {}", self.version);
+        }
+        Ok(BASE64_URL_SAFE_NO_PAD.decode(self.key.trim_end_matches('='))?)
+    }
+}
+
+/// Short fingerprint used as the key id; for key pairs pass the public key
+pub fn key_id(key: &[u8]) -> String {
+    let hash = blake3::derive_key(KEY_ID_CONTEXT, key);
+    data_encoding::HEXLOWER.encode(&hash[..8])
+}
+
+/// Load `N` bytes of key material for `algorithm` from a key envelope, hex, base64 or legacy raw file
+pub fn load_key_material<const N: usize>(
+    path: impl AsRef<Path>,
+    algorithm: &str,
+) -> Result<[u8; N]> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    let key = decode_key_material(&data, algorithm, N).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load {} key from {}: {}",
+            algorithm,
+            path.display(),
+            e
+        )
+    })?;
+    key.try_into().map_err(|key: Vec<u8>| {
+        anyhow::anyhow!(
+            "Failed to load {} key from {}: expected {} bytes, got {}",
+            algorithm,
+            path.display(),
+            N,
+            key.len()
+        )
+    })
+}
+
+/// Load a shared secret of any length: a key envelope, or the raw file minus its trailing newline
+pub fn load_secret(path: impl AsRef<Path>, algorithm: &str) -> Result<Vec<u8>> {
+    read_secret(path.as_ref(), Some(algorithm))
+}
+
+/// Load a master secret to derive keys from, accepting a key envelope of any algorithm
+pub fn load_master_secret(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    read_secret(path.as_ref(), None)
+}
+
+fn read_secret(path: &Path, algorithm: Option<&str>) -> Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    let name = algorithm.unwrap_or("master");
+
+    let secret = match std::str::from_utf8(&data).map(str::trim) {
+        Ok(text) if text.starts_with('{') => {
+            let envelope: KeyEnvelope = serde_json::from_str(text)?;
+            if algorithm.is_some_and(|algorithm| envelope.algorithm != algorithm) {
+                anyhow::bail!(
+                    "Failed to load {} key from {}: key file is for {}",
+                    name,
+                    path.display(),
+                    envelope.algorithm
+                );
+            }
+            envelope.material()?
+        }
+        _ => {
+            let data = data.strip_suffix(b"\n").unwrap_or(&data);
+            data.strip_suffix(b"\r").unwrap_or(data).to_vec()
+        }
+    };
+
+    if secret.is_empty() {
+        anyhow::bail!(
+            "Failed to load {} key from {}: key is empty",
+            name,
+            path.display()
+        );
+    }
+    Ok(secret)
+}
+
+/// Decode `expected` bytes from a key envelope for `algorithm`, or bare raw, hex or base64 data
+pub fn decode_key_material(data: &[u8], algorithm: &str, expected: usize) -> Result<Vec<u8>> {
+    let text = std::str::from_utf8(data).map(str::trim);
+
+    if let Ok(text) = text {
+        if text.starts_with('{') {
+            let envelope: KeyEnvelope = serde_json::from_str(text)?;
+            if envelope.algorithm != algorithm {
+                anyhow::bail!("key file is for {}", envelope.algorithm);
+            }
+            return envelope.material();
+        }
+    }
+
+    // legacy files are the raw key bytes, possibly saved with a trailing newline
+    if data.len() == expected {
+        return Ok(data.to_vec());
+    }
+    let raw = data.strip_suffix(b"\n").unwrap_or(data);
+    let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+    if raw.len() == expected {
+        return Ok(raw.to_vec());
+    }
+    if let Ok(text) = text {
+        if text.len() == expected * 2 {
+            if let Ok(key) = data_encoding::HEXLOWER_PERMISSIVE.decode(text.as_bytes()) {
+                return Ok(key);
+            }
+        }
+        for engine in [
+            &BASE64_STANDARD,
+            &BASE64_URL_SAFE,
+            &BASE64_STANDARD_NO_PAD,
+            &BASE64_URL_SAFE_NO_PAD,
+        ] {
+            if let Ok(key) = engine.decode(text) {
+                return Ok(key);
+            }
+        }
+    }
+
+    anyhow::bail!(
+        "expected a key file, {} hex digits, base64 or {} raw bytes, got {} bytes",
+        expected * 2,
+        expected,
+        data.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn write_temp(data: &[u8]) -> Result<NamedTempFile> {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(data)?;
+        Ok(file)
+    }
+
+    #[test]
+    fn test_load_key_material_formats() -> Result<()> {
+        let raw = fs::read("fixtures/blake3.key")?;
+        assert_eq!(
+            load_key_material::<32>("fixtures/blake3.key", BLAKE3_KEY)?,
+            raw[..]
+        );
+
+        let envelope = KeyEnvelope::new(BLAKE3_KEY, &key_id(&raw), &raw).to_vec()?;
+        let path = write_temp(&envelope)?;
+        assert_eq!(load_key_material::<32>(&path, BLAKE3_KEY)?, raw[..]);
+
+        let hex = format!("{}\n", data_encoding::HEXLOWER.encode(&raw));
+        let path = write_temp(hex.as_bytes())?;
+        assert_eq!(load_key_material::<32>(&path, BLAKE3_KEY)?, raw[..]);
+
+        let b64 = BASE64_STANDARD.encode(&raw);
+        let path = write_temp(b64.as_bytes())?;
+        assert_eq!(load_key_material::<32>(&path, BLAKE3_KEY)?, raw[..]);
+
+        for newline in [&b"\n"[..], b"\r\n"] {
+            let path = write_temp(&[&raw[..], newline].concat())?;
+            assert_eq!(load_key_material::<32>(&path, BLAKE3_KEY)?, raw[..]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_key_material_errors() -> Result<()> {
+        let path = write_temp(b"too short")?;
+        assert!(load_key_material::<32>(&path, BLAKE3_KEY).is_err());
+
+        let envelope = KeyEnvelope::new(ED25519_PUBLIC_KEY, "id", &[0u8; 32]).to_vec()?;
+        let path = write_temp(&envelope)?;
+        let err = load_key_material::<32>(&path, ED25519_SECRET_KEY).unwrap_err();
+        assert!(err.to_string().contains("key file is for ed25519-pk"));
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    command::DigestAlgorithm,
+    process::{
+        format_checksum_line, hash_files, parse_checksum_line, walk_files, Ed25519Signer,
+        Ed25519Verifier, TextSign, TextVerify,
+    },
+    KeyLoader,
+};
+use anyhow::Result;
+use base64::prelude::*;
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestStatus {
+    Ok,
+    Modified,
+    Missing,
+    Extra,
+}
+
+/// Write a b3sum-compatible manifest of every file under `dir` and an Ed25519 signature over it
+pub fn process_text_sign_dir(dir: &Path, key: &str, manifest: &Path, sig: &Path) -> Result<usize> {
+    let signer = Ed25519Signer::load(key)?;
+    let digests = hash_dir(dir, manifest, sig)?;
+
+    let mut content = String::new();
+    for (path, hash) in &digests {
+        content.push_str(&format_checksum_line(hash, path));
+        content.push('\n');
+    }
+    let signature = signer.sign(&mut content.as_bytes())?;
+
+    fs::write(manifest, &content)?;
+    fs::write(
+        sig,
+        format!("{}\n", BASE64_URL_SAFE_NO_PAD.encode(signature)),
+    )?;
+    Ok(digests.len())
+}
+
+/// Check the manifest signature, then compare the manifest against the files under `dir`
+pub fn process_text_verify_dir(
+    dir: &Path,
+    key: &str,
+    manifest: &Path,
+    sig: &Path,
+) -> Result<Vec<(String, ManifestStatus)>> {
+    let verifier = Ed25519Verifier::load(key)?;
+    let content = fs::read_to_string(manifest)?;
+    let signature = BASE64_URL_SAFE_NO_PAD.decode(fs::read_to_string(sig)?.trim())?;
+    if !verifier.verify(&mut content.as_bytes(), &signature)? {
+        anyhow::bail!("Manifest signature is invalid: {}", manifest.display());
+    }
+
+    let mut expected = BTreeMap::new();
+    for (n, line) in content.lines().enumerate() {
+        let (hash, path) = parse_checksum_line(line)
+            .ok_or_else(|| anyhow::anyhow!("Malformed manifest on line {}", n + 1))?;
+        expected.insert(path, hash);
+    }
+
+    let mut actual = hash_dir(dir, manifest, sig)?;
+    let mut results = Vec::new();
+    for (path, hash) in expected {
+        let status = match actual.remove(&path) {
+            Some(actual) if actual == hash => ManifestStatus::Ok,
+            Some(_) => ManifestStatus::Modified,
+            None => ManifestStatus::Missing,
+        };
+        results.push((path, status));
+    }
+    results.extend(actual.into_keys().map(|path| (path, ManifestStatus::Extra)));
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(results)
+}
+
+/// BLAKE3 of every file under `dir` keyed by its `/`-separated relative path, skipping the
+/// manifest and signature when they live inside the directory
+fn hash_dir(dir: &Path, manifest: &Path, sig: &Path) -> Result<BTreeMap<String, String>> {
+    let skip: Vec<_> = [manifest, sig]
+        .iter()
+        .filter_map(|p| p.canonicalize().ok())
+        .collect();
+    // a manifest must cover everything in the tree, so links are refused rather than skipped
+    let files = walk_files(dir, true)?
+        .into_iter()
+        .filter(|f| {
+            Path::new(f)
+                .canonicalize()
+                .map_or(true, |f| !skip.contains(&f))
+        })
+        .collect();
+
+    let mut digests = BTreeMap::new();
+    for digest in hash_files(files, DigestAlgorithm::Blake3)? {
+        let relative = Path::new(&digest.path).strip_prefix(dir)?;
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        digests.insert(relative, digest.hash);
+    }
+    Ok(digests)
+}
+
+impl fmt::Display for ManifestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestStatus::Ok => write!(f, "OK"),
+            ManifestStatus::Modified => write!(f, "MODIFIED"),
+            ManifestStatus::Missing => write!(f, "MISSING"),
+            ManifestStatus::Extra => write!(f, "EXTRA"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify_dir() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("nested"))?;
+        fs::write(dir.join("a.txt"), b"alpha")?;
+        fs::write(dir.join("b.txt"), b"beta")?;
+        fs::write(dir.join("nested/c.txt"), b"gamma")?;
+        let (manifest, sig) = (dir.join("MANIFEST.b3"), dir.join("MANIFEST.b3.sig"));
+
+        assert_eq!(
+            process_text_sign_dir(dir, "fixtures/ed25519.sk", &manifest, &sig)?,
+            3
+        );
+        let results = process_text_verify_dir(dir, "fixtures/ed25519.pk", &manifest, &sig)?;
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|(_, s)| *s == ManifestStatus::Ok));
+        assert_eq!(results[2].0, "nested/c.txt");
+
+        fs::write(dir.join("a.txt"), b"ALPHA")?;
+        fs::remove_file(dir.join("b.txt"))?;
+        fs::write(dir.join("d.txt"), b"delta")?;
+        let results = process_text_verify_dir(dir, "fixtures/ed25519.pk", &manifest, &sig)?;
+        assert_eq!(
+            results,
+            vec![
+                ("a.txt".to_string(), ManifestStatus::Modified),
+                ("b.txt".to_string(), ManifestStatus::Missing),
+                ("d.txt".to_string(), ManifestStatus::Extra),
+                ("nested/c.txt".to_string(), ManifestStatus::Ok),
+            ]
+        );
+
+        // editing the manifest to match breaks its signature
+        let content = fs::read_to_string(&manifest)?.replace("  b.txt\n", "  d.txt\n");
+        fs::write(&manifest, content)?;
+        assert!(process_text_verify_dir(dir, "fixtures/ed25519.pk", &manifest, &sig).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sign_dir_escapes_paths_rejects_symlinks() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        fs::write(dir.join("line\nbreak.txt"), b"alpha")?;
+        fs::write(dir.join("back\\slash.txt"), b"beta")?;
+        let (manifest, sig) = (dir.join("MANIFEST.b3"), dir.join("MANIFEST.b3.sig"));
+
+        assert_eq!(
+            process_text_sign_dir(dir, "fixtures/ed25519.sk", &manifest, &sig)?,
+            2
+        );
+        assert_eq!(fs::read_to_string(&manifest)?.lines().count(), 2);
+        let results = process_text_verify_dir(dir, "fixtures/ed25519.pk", &manifest, &sig)?;
+        assert_eq!(
+            results,
+            vec![
+                ("back\\slash.txt".to_string(), ManifestStatus::Ok),
+                ("line\nbreak.txt".to_string(), ManifestStatus::Ok),
+            ]
+        );
+
+        std::os::unix::fs::symlink("/etc/passwd", dir.join("link"))?;
+        let err = process_text_sign_dir(dir, "fixtures/ed25519.sk", &manifest, &sig).unwrap_err();
+        assert!(err.to_string().contains("symbolic links"));
+        Ok(())
+    }
+}
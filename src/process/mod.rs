@@ -1,13 +1,43 @@
+mod age;
 mod b64;
+mod breached;
+mod codec;
+mod compress;
 mod csv_convert;
+mod derive;
+mod gen_id;
 mod gen_pass;
+mod hash;
+mod hashpw;
+mod hexdump;
 mod http_serve;
 mod jwt;
+mod key_convert;
+mod key_file;
+mod manifest;
+mod otp;
+mod stream;
 mod text;
+mod url;
 
+pub use age::*;
 pub use b64::*;
+pub use breached::*;
+pub use codec::*;
+pub use compress::*;
 pub use csv_convert::*;
+pub use derive::*;
+pub use gen_id::*;
 pub use gen_pass::*;
+pub use hash::*;
+pub use hashpw::*;
+pub use hexdump::*;
 pub use http_serve::*;
 pub use jwt::*;
+pub use key_convert::*;
+pub use key_file::*;
+pub use manifest::*;
+pub use otp::*;
+pub use stream::*;
 pub use text::*;
+pub use url::*;
@@ -0,0 +1,232 @@
+use crate::command::OtpAlgorithm;
+use anyhow::Result;
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::{rngs::OsRng, RngCore};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+/// Largest drift `process_otp_verify` accepts, in steps either side
+pub const MAX_OTP_WINDOW: u64 = 100;
+
+#[derive(Debug, Clone, Copy)]
+pub struct OtpConfig {
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+}
+
+/// Compute the RFC 4226 HOTP value for the given counter
+pub fn hotp(secret: &[u8], counter: u64, algorithm: OtpAlgorithm, digits: u32) -> Result<String> {
+    let counter = counter.to_be_bytes();
+    let digest = match algorithm {
+        OtpAlgorithm::Sha1 => hmac_digest::<Hmac<Sha1>>(secret, &counter)?,
+        OtpAlgorithm::Sha256 => hmac_digest::<Hmac<Sha256>>(secret, &counter)?,
+        OtpAlgorithm::Sha512 => hmac_digest::<Hmac<Sha512>>(secret, &counter)?,
+    };
+
+    // dynamic truncation
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes(digest[offset..offset + 4].try_into()?) & 0x7fff_ffff;
+    let code = binary as u64 % 10u64.pow(digits);
+
+    Ok(format!("{:0width$}", code, width = digits as usize))
+}
+
+/// Compute the RFC 6238 TOTP value for the given unix time
+pub fn totp(secret: &[u8], time: u64, config: OtpConfig) -> Result<String> {
+    hotp(
+        secret,
+        time / config.period,
+        config.algorithm,
+        config.digits,
+    )
+}
+
+fn hmac_digest<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = <M as Mac>::new_from_slice(key)?;
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>> {
+    let secret: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .collect::<String>()
+        .to_uppercase();
+    let secret = BASE32_NOPAD.decode(secret.as_bytes())?;
+    if secret.is_empty() {
+        anyhow::bail!("Secret must not be empty");
+    }
+    Ok(secret)
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp() as u64
+}
+
+/// Generate a new base32 secret and the matching `otpauth://` URI
+pub fn process_otp_generate(
+    issuer: &str,
+    account: &str,
+    config: OtpConfig,
+    counter: Option<u64>,
+) -> Result<(String, String)> {
+    let mut key = [0u8; 20];
+    OsRng.fill_bytes(&mut key);
+    let secret = BASE32_NOPAD.encode(&key);
+
+    let label = format!(
+        "{}:{}",
+        utf8_percent_encode(issuer, NON_ALPHANUMERIC),
+        utf8_percent_encode(account, NON_ALPHANUMERIC)
+    );
+    let kind = match counter {
+        Some(counter) => format!("hotp/{}?counter={}&", label, counter),
+        None => format!("totp/{}?period={}&", label, config.period),
+    };
+    let uri = format!(
+        "otpauth://{}secret={}&issuer={}&algorithm={}&digits={}",
+        kind,
+        secret,
+        utf8_percent_encode(issuer, NON_ALPHANUMERIC),
+        config.algorithm.to_string().to_uppercase(),
+        config.digits
+    );
+
+    Ok((secret, uri))
+}
+
+/// Produce the current TOTP, or the HOTP for `counter` when given
+pub fn process_otp_code(secret: &str, config: OtpConfig, counter: Option<u64>) -> Result<String> {
+    let secret = decode_secret(secret)?;
+    match counter {
+        Some(counter) => hotp(&secret, counter, config.algorithm, config.digits),
+        None => totp(&secret, now(), config),
+    }
+}
+
+/// Verify a code, accepting up to `window` steps of drift (look-ahead only for HOTP), and
+/// return the counter (or TOTP time step) it matched so HOTP callers can resync
+pub fn process_otp_verify(
+    secret: &str,
+    code: &str,
+    config: OtpConfig,
+    counter: Option<u64>,
+    window: u64,
+) -> Result<Option<u64>> {
+    if window > MAX_OTP_WINDOW {
+        anyhow::bail!("Window must be at most {} steps", MAX_OTP_WINDOW);
+    }
+    let secret = decode_secret(secret)?;
+    let candidates = match counter {
+        Some(counter) => counter..=counter.saturating_add(window),
+        None => {
+            let step = now() / config.period;
+            step.saturating_sub(window)..=step.saturating_add(window)
+        }
+    };
+
+    // check every candidate so the timing does not reveal which one matched
+    let mut matched = None;
+    for counter in candidates {
+        let expected = hotp(&secret, counter, config.algorithm, config.digits)?;
+        if constant_time_eq(expected.as_bytes(), code.as_bytes()) && matched.is_none() {
+            matched = Some(counter);
+        }
+    }
+
+    Ok(matched)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA1_SECRET: &[u8] = b"12345678901234567890";
+    const SHA256_SECRET: &[u8] = b"12345678901234567890123456789012";
+    const SHA512_SECRET: &[u8] =
+        b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    #[test]
+    fn test_hotp_rfc4226_vectors() -> Result<()> {
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(
+                hotp(SHA1_SECRET, counter as u64, OtpAlgorithm::Sha1, 6)?,
+                *code
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_totp_rfc6238_vectors() -> Result<()> {
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, sha1, sha256, sha512) in vectors {
+            for (secret, algorithm, code) in [
+                (SHA1_SECRET, OtpAlgorithm::Sha1, sha1),
+                (SHA256_SECRET, OtpAlgorithm::Sha256, sha256),
+                (SHA512_SECRET, OtpAlgorithm::Sha512, sha512),
+            ] {
+                let config = OtpConfig {
+                    algorithm,
+                    digits: 8,
+                    period: 30,
+                };
+                assert_eq!(totp(secret, time, config)?, code);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_otp_generate_and_verify() -> Result<()> {
+        let config = OtpConfig {
+            algorithm: OtpAlgorithm::Sha256,
+            digits: 6,
+            period: 30,
+        };
+        let (secret, uri) = process_otp_generate("ACME Co", "alice@example.com", config, None)?;
+        assert!(uri.starts_with("otpauth://totp/ACME%20Co:alice%40example%2Ecom?"));
+        assert!(uri.contains(&format!("secret={}", secret)));
+
+        let code = process_otp_code(&secret, config, None)?;
+        assert!(process_otp_verify(&secret, &code, config, None, 1)?.is_some());
+
+        let code = process_otp_code(&secret, config, Some(7))?;
+        assert_eq!(
+            process_otp_verify(&secret, &code, config, Some(5), 2)?,
+            Some(7)
+        );
+        assert_eq!(
+            process_otp_verify(&secret, &code, config, Some(5), 1)?,
+            None
+        );
+        assert!(process_otp_verify(&secret, &code, config, Some(5), MAX_OTP_WINDOW + 1).is_err());
+
+        // an empty HMAC key would still give a valid looking code
+        for secret in ["", "  ", "===="] {
+            let err = process_otp_code(secret, config, Some(0)).unwrap_err();
+            assert_eq!(err.to_string(), "Secret must not be empty");
+        }
+        Ok(())
+    }
+}
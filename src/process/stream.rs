@@ -0,0 +1,417 @@
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::{generic_array::GenericArray, Aead, AeadCore, Payload};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::io::{self, Read, Write};
+
+pub const STREAM_MAGIC: &[u8; 8] = b"RCLIAEAD";
+const STREAM_VERSION: u8 = 2;
+pub const STREAM_SALT_LEN: usize = 16;
+const STREAM_KEY_INFO: &[u8] = b"rcli stream payload key";
+/// Plaintext bytes per chunk, each chunk grows by a 16 byte tag when sealed
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+/// The nonce ends with a 32-bit chunk counter and a final-chunk flag
+const NONCE_SUFFIX: usize = 5;
+
+const KEY_SOURCE_FILE: u8 = 0;
+const KEY_SOURCE_ARGON2ID: u8 = 1;
+pub const PASSPHRASE_SALT_LEN: usize = 16;
+/// A forged header must not make decryption exhaust memory or CPU before anything is
+/// authenticated, so each cost is capped at four times the encrypt defaults (64 MiB, 3, 4)
+const MAX_ARGON2_MEMORY: u32 = 256 * 1024;
+const MAX_ARGON2_ITERATIONS: u32 = 12;
+const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+/// Where the stream key comes from; a passphrase keeps everything needed to re-derive it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    File,
+    Argon2id {
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+        salt: [u8; PASSPHRASE_SALT_LEN],
+    },
+}
+
+/// Versioned header in front of every chunked ciphertext; it is authenticated, followed by any
+/// caller supplied associated data, as the AAD of each chunk
+///
+/// `magic (8) | version (1) | cipher (1) | key source (1) | [kdf params] | salt (16) | nonce prefix`
+///
+/// Argon2id params are memory, iterations and parallelism as big-endian u32s, then the salt.
+/// Every stream is sealed under its own subkey derived from the salt, so the short random nonce
+/// prefix never has to be unique across all streams of a long-lived key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamHeader {
+    pub cipher: u8,
+    pub key_source: KeySource,
+    pub salt: [u8; STREAM_SALT_LEN],
+    pub nonce_prefix: Vec<u8>,
+}
+
+impl StreamHeader {
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut header = STREAM_MAGIC.to_vec();
+        header.extend_from_slice(&[STREAM_VERSION, self.cipher]);
+        match &self.key_source {
+            KeySource::File => header.push(KEY_SOURCE_FILE),
+            KeySource::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+                salt,
+            } => {
+                header.push(KEY_SOURCE_ARGON2ID);
+                for param in [memory, iterations, parallelism] {
+                    header.extend_from_slice(&param.to_be_bytes());
+                }
+                header.extend_from_slice(salt);
+            }
+        }
+        header.extend_from_slice(&self.salt);
+        header.extend_from_slice(&self.nonce_prefix);
+        header
+    }
+
+    /// The key this stream is sealed with: HKDF-SHA256 of `key` under the header salt
+    pub fn stream_key(&self, key: &[u8; 32]) -> [u8; 32] {
+        let mut okm = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&self.salt), key)
+            .expand(STREAM_KEY_INFO, &mut okm)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        okm
+    }
+
+    /// Parse a header, given the nonce length of each cipher id
+    pub fn read_from(
+        reader: &mut dyn Read,
+        nonce_len: impl Fn(u8) -> Option<usize>,
+    ) -> Result<Self> {
+        let mut fixed = [0u8; 11];
+        reader.read_exact(&mut fixed)?;
+        if &fixed[..8] != STREAM_MAGIC {
+            anyhow::bail!("Not an rcli encrypted stream");
+        }
+        let version = fixed[8];
+        if version != STREAM_VERSION {
+            anyhow::bail!("Unsupported stream version: {}", version);
+        }
+        let cipher = fixed[9];
+        let len = nonce_len(cipher).ok_or_else(|| anyhow!("Unsupported cipher id: {}", cipher))?;
+        let key_source = match fixed[10] {
+            KEY_SOURCE_FILE => KeySource::File,
+            KEY_SOURCE_ARGON2ID => {
+                let mut params = [0u8; 12 + PASSPHRASE_SALT_LEN];
+                reader.read_exact(&mut params)?;
+                let param = |i: usize| u32::from_be_bytes(params[i..i + 4].try_into().unwrap());
+                let (memory, iterations, parallelism) = (param(0), param(4), param(8));
+                if memory > MAX_ARGON2_MEMORY {
+                    anyhow::bail!(
+                        "Argon2 memory cost in the header is too large: {} KiB",
+                        memory
+                    );
+                }
+                if iterations > MAX_ARGON2_ITERATIONS {
+                    anyhow::bail!(
+                        "Argon2 time cost in the header is too large: {}",
+                        iterations
+                    );
+                }
+                if parallelism > MAX_ARGON2_PARALLELISM {
+                    anyhow::bail!(
+                        "Argon2 parallelism in the header is too large: {}",
+                        parallelism
+                    );
+                }
+                KeySource::Argon2id {
+                    memory,
+                    iterations,
+                    parallelism,
+                    salt: params[12..].try_into()?,
+                }
+            }
+            v => anyhow::bail!("Unsupported key source: {}", v),
+        };
+        let mut salt = [0u8; STREAM_SALT_LEN];
+        reader.read_exact(&mut salt)?;
+        let mut nonce_prefix = vec![0u8; len - NONCE_SUFFIX];
+        reader.read_exact(&mut nonce_prefix)?;
+        Ok(Self {
+            cipher,
+            key_source,
+            salt,
+            nonce_prefix,
+        })
+    }
+}
+
+/// Seal `reader` chunk by chunk (STREAM construction), holding at most two chunks in memory
+pub fn encrypt_stream<A: Aead>(
+    cipher: &A,
+    header: &StreamHeader,
+    associated_data: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let mut aad = header.to_vec();
+    writer.write_all(&aad)?;
+    aad.extend_from_slice(associated_data);
+    seal_chunks(cipher, &header.nonce_prefix, &aad, reader, writer)
+}
+
+/// Seal `reader` chunk by chunk (STREAM construction) without writing any header, every chunk
+/// nonce starts with `nonce_prefix` and every chunk is authenticated with `aad`
+pub fn seal_chunks<A: Aead>(
+    cipher: &A,
+    nonce_prefix: &[u8],
+    aad: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let mut current = vec![0u8; CHUNK_SIZE];
+    let mut next = vec![0u8; CHUNK_SIZE];
+    let mut len = read_full(reader, &mut current)?;
+    for counter in 0u32.. {
+        // only a full chunk can be followed by more data
+        let next_len = if len == CHUNK_SIZE {
+            read_full(reader, &mut next)?
+        } else {
+            0
+        };
+        let last = next_len == 0;
+        let nonce = chunk_nonce::<A>(nonce_prefix, counter, last);
+        let sealed = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &current[..len],
+                    aad,
+                },
+            )
+            .map_err(|_| anyhow!("Encryption failed"))?;
+        writer.write_all(&sealed)?;
+        if last {
+            writer.flush()?;
+            return Ok(());
+        }
+        std::mem::swap(&mut current, &mut next);
+        len = next_len;
+    }
+    anyhow::bail!("Input too large for a single stream")
+}
+
+/// Open a stream whose header was already parsed, failing on any modified, reordered or
+/// truncated chunk
+pub fn decrypt_stream<A: Aead>(
+    cipher: &A,
+    header: &StreamHeader,
+    associated_data: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let mut aad = header.to_vec();
+    aad.extend_from_slice(associated_data);
+    open_chunks(cipher, &header.nonce_prefix, &aad, reader, writer)
+}
+
+/// Open chunks written by [`seal_chunks`]
+pub fn open_chunks<A: Aead>(
+    cipher: &A,
+    nonce_prefix: &[u8],
+    aad: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let mut current = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+    let mut next = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+    let mut len = read_full(reader, &mut current)?;
+    for counter in 0u32.. {
+        let next_len = if len == current.len() {
+            read_full(reader, &mut next)?
+        } else {
+            0
+        };
+        let last = next_len == 0;
+        let nonce = chunk_nonce::<A>(nonce_prefix, counter, last);
+        let plain = cipher
+            .decrypt(
+                &nonce,
+                Payload {
+                    msg: &current[..len],
+                    aad,
+                },
+            )
+            .map_err(|_| anyhow!("Decryption failed: wrong key, or corrupted or truncated data"))?;
+        // the sealer never ends a non-empty stream with an empty chunk
+        if last && counter > 0 && plain.is_empty() {
+            anyhow::bail!("Decryption failed: unexpected empty final chunk");
+        }
+        writer.write_all(&plain)?;
+        if last {
+            writer.flush()?;
+            return Ok(());
+        }
+        std::mem::swap(&mut current, &mut next);
+        len = next_len;
+    }
+    anyhow::bail!("Input too large for a single stream")
+}
+
+fn chunk_nonce<A: AeadCore>(
+    prefix: &[u8],
+    counter: u32,
+    last: bool,
+) -> GenericArray<u8, A::NonceSize> {
+    let mut nonce = GenericArray::default();
+    nonce[..prefix.len()].copy_from_slice(prefix);
+    nonce[prefix.len()..prefix.len() + 4].copy_from_slice(&counter.to_be_bytes());
+    nonce[prefix.len() + 4] = last as u8;
+    nonce
+}
+
+/// Fill `buf` unless the reader ends first, returning how much was read
+pub fn read_full(reader: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Size of the nonce prefix stored in the header for a cipher with `nonce_len` byte nonces
+pub fn nonce_prefix_len(nonce_len: usize) -> usize {
+    nonce_len - NONCE_SUFFIX
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chacha20poly1305::{aead::KeyInit, ChaCha20Poly1305};
+
+    fn seal(cipher: &ChaCha20Poly1305, data: &[u8]) -> Result<(StreamHeader, Vec<u8>)> {
+        let header = StreamHeader {
+            cipher: 1,
+            key_source: KeySource::File,
+            salt: [9u8; STREAM_SALT_LEN],
+            nonce_prefix: vec![7u8; nonce_prefix_len(12)],
+        };
+        let mut sealed = Vec::new();
+        encrypt_stream(cipher, &header, b"", &mut &data[..], &mut sealed)?;
+        Ok((header, sealed))
+    }
+
+    fn open(cipher: &ChaCha20Poly1305, mut sealed: &[u8]) -> Result<Vec<u8>> {
+        let header = StreamHeader::read_from(&mut sealed, |_| Some(12))?;
+        let mut plain = Vec::new();
+        decrypt_stream(cipher, &header, b"", &mut sealed, &mut plain)?;
+        Ok(plain)
+    }
+
+    #[test]
+    fn test_stream_chunk_boundaries() -> Result<()> {
+        let cipher = ChaCha20Poly1305::new(&[42u8; 32].into());
+        for len in [
+            0,
+            1,
+            CHUNK_SIZE - 1,
+            CHUNK_SIZE,
+            CHUNK_SIZE + 1,
+            3 * CHUNK_SIZE,
+        ] {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let (header, sealed) = seal(&cipher, &data)?;
+            let chunks = len.div_ceil(CHUNK_SIZE).max(1);
+            assert_eq!(
+                sealed.len(),
+                header.to_vec().len() + len + chunks * TAG_SIZE
+            );
+            assert_eq!(open(&cipher, &sealed)?, data, "length {}", len);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_header_salt_and_version() -> Result<()> {
+        let key = [42u8; 32];
+        let mut header = StreamHeader {
+            cipher: 1,
+            key_source: KeySource::File,
+            salt: [1u8; STREAM_SALT_LEN],
+            nonce_prefix: vec![7u8; nonce_prefix_len(12)],
+        };
+        let bytes = header.to_vec();
+        assert_eq!(bytes[8], STREAM_VERSION);
+        assert_eq!(
+            StreamHeader::read_from(&mut &bytes[..], |_| Some(12))?,
+            header
+        );
+
+        // each salt gives its own stream key
+        let first = header.stream_key(&key);
+        header.salt = [2u8; STREAM_SALT_LEN];
+        assert_ne!(header.stream_key(&key), first);
+        assert_ne!(first, key);
+
+        // any other version is refused
+        let mut bytes = header.to_vec();
+        bytes[8] = 1;
+        assert!(StreamHeader::read_from(&mut &bytes[..], |_| Some(12)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_header_caps_argon2_costs() {
+        for (memory, iterations, parallelism) in [
+            (MAX_ARGON2_MEMORY + 1, 3, 4),
+            (64 * 1024, u32::MAX, 4),
+            (64 * 1024, 3, MAX_ARGON2_PARALLELISM + 1),
+        ] {
+            let header = StreamHeader {
+                cipher: 1,
+                key_source: KeySource::Argon2id {
+                    memory,
+                    iterations,
+                    parallelism,
+                    salt: [0u8; PASSPHRASE_SALT_LEN],
+                },
+                salt: [1u8; STREAM_SALT_LEN],
+                nonce_prefix: vec![7u8; nonce_prefix_len(12)],
+            };
+            let bytes = header.to_vec();
+            assert!(StreamHeader::read_from(&mut &bytes[..], |_| Some(12)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_stream_rejects_tampering() -> Result<()> {
+        let cipher = ChaCha20Poly1305::new(&[42u8; 32].into());
+        let data = vec![1u8; 2 * CHUNK_SIZE + 10];
+        let (header, sealed) = seal(&cipher, &data)?;
+        let header_len = header.to_vec().len();
+
+        // dropping the final chunk leaves a non-final chunk at the end
+        let truncated = &sealed[..header_len + CHUNK_SIZE + TAG_SIZE];
+        assert!(open(&cipher, truncated).is_err());
+
+        // swapping two chunks breaks their counters
+        let mut reordered = sealed[..header_len].to_vec();
+        let chunk = CHUNK_SIZE + TAG_SIZE;
+        reordered.extend_from_slice(&sealed[header_len + chunk..header_len + 2 * chunk]);
+        reordered.extend_from_slice(&sealed[header_len..header_len + chunk]);
+        reordered.extend_from_slice(&sealed[header_len + 2 * chunk..]);
+        assert!(open(&cipher, &reordered).is_err());
+
+        // the header is authenticated
+        let mut modified = sealed.clone();
+        modified[header_len - 1] ^= 1;
+        assert!(open(&cipher, &modified).is_err());
+        Ok(())
+    }
+}
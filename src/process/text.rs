@@ -1,21 +1,61 @@
-use crate::{command::TextSignFormat, get_reader, process_gen_pass, KeyLoader};
+use crate::{
+    command::{AeadCipher, TextSignFormat},
+    get_reader,
+    process::{
+        base64_auto_reader, decrypt_stream, encrypt_stream, for_each_chunk, key_id,
+        load_key_material, load_secret, nonce_prefix_len, process_age_decrypt, process_pem_decode,
+        process_pem_encode, read_full, AgeIdentity, Ed25519Key, KeyEnvelope, KeySource,
+        StreamHeader, AGE_MAGIC, BLAKE3_KEY, CHACHA20_KEY, ED25519_PUBLIC_KEY, ED25519_SECRET_KEY,
+        HMAC_KEY, PASSPHRASE_SALT_LEN, STREAM_MAGIC, STREAM_SALT_LEN,
+    },
+    KeyLoader,
+};
+use aes_gcm::Aes256Gcm;
+use aes_gcm_siv::Aes256GcmSiv;
 use anyhow::{anyhow, Ok, Result};
 use base64::prelude::*;
 use chacha20poly1305::{
-    aead::{generic_array::GenericArray, Aead, AeadCore, KeyInit, OsRng},
-    ChaCha20Poly1305,
+    aead::{generic_array::GenericArray, Aead, KeyInit, OsRng},
+    ChaCha20Poly1305, XChaCha20Poly1305,
 };
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
-use std::{fs, io::Read, path::Path};
+use ed448_goldilocks_plus::pkcs8::{
+    DecodePrivateKey as _, DecodePublicKey as _, EncodePrivateKey as _, EncodePublicKey as _,
+};
+use hmac::{Hmac, Mac};
+use p256::pkcs8::{
+    DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding,
+};
+use rand::RngCore;
+use rsa::{
+    pss::{BlindedSigningKey, VerifyingKey as RsaVerifyingKey},
+    signature::{DigestSigner, DigestVerifier, RandomizedDigestSigner, SignatureEncoding},
+    RsaPrivateKey, RsaPublicKey,
+};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use sha3::Sha3_256;
+use std::{
+    fmt::Display,
+    fs,
+    io::{Read, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+const RSA_KEY_BITS: usize = 3072;
+// RFC 9106 second recommended option, 64 MiB
+const PASSPHRASE_MEMORY: u32 = 64 * 1024;
+const PASSPHRASE_ITERATIONS: u32 = 3;
+const PASSPHRASE_PARALLELISM: u32 = 4;
 
 pub trait TextSign {
-    /// Sign the input data
+    /// Sign the input data, streaming it where the algorithm allows
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
 }
 
 pub trait TextVerify {
-    /// Verify the input data
-    fn verify(&self, reader: impl Read, sig: &[u8]) -> Result<bool>;
+    /// Verify the input data, streaming it where the algorithm allows
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool>;
 }
 
 pub trait KeyGenerator {
@@ -23,17 +63,30 @@ pub trait KeyGenerator {
 }
 
 pub trait TextEncrypt {
-    fn encrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
+    /// Encrypt the input as a chunked stream with a header
+    fn encrypt(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()>;
 }
 
 pub trait TextDecrypt {
-    fn decrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
+    /// Decrypt a chunked stream, header included
+    fn decrypt(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()>;
 }
 
 pub struct Blake3 {
     key: [u8; 32],
 }
 
+/// Keyed MAC over a shared secret of any length, `M` picks the hash
+pub struct HmacSigner<M> {
+    key: Vec<u8>,
+    _mac: PhantomData<M>,
+}
+
+pub type HmacSha256 = HmacSigner<Hmac<Sha256>>;
+pub type HmacSha384 = HmacSigner<Hmac<Sha384>>;
+pub type HmacSha512 = HmacSigner<Hmac<Sha512>>;
+pub type HmacSha3_256 = HmacSigner<Hmac<Sha3_256>>;
+
 pub struct Ed25519Signer {
     key: SigningKey,
 }
@@ -42,47 +95,155 @@ pub struct Ed25519Verifier {
     key: VerifyingKey,
 }
 
-pub struct ChaCha20 {
+/// Ed25519ph (RFC 8032): signs a SHA-512 of the message, so the input is streamed
+pub struct Ed25519phSigner {
+    key: SigningKey,
+}
+
+pub struct Ed25519phVerifier {
+    key: VerifyingKey,
+}
+
+/// A 256-bit key for any of the stream AEADs, plus the associated data to bind
+pub struct StreamCipher {
     key: [u8; 32],
+    source: KeySource,
+    cipher: AeadCipher,
+    aad: Vec<u8>,
+}
+
+/// How the user supplied the encryption key
+pub enum EncryptionKey {
+    File(String),
+    Passphrase(String),
+    /// age `age1...` recipients to encrypt to
+    Recipients(Vec<String>),
+    /// age identity files to decrypt with
+    Identities(Vec<String>),
+}
+
+pub struct P256Signer {
+    key: p256::ecdsa::SigningKey,
+}
+
+pub struct P256Verifier {
+    key: p256::ecdsa::VerifyingKey,
+}
+
+pub struct P384Signer {
+    key: p384::ecdsa::SigningKey,
+}
+
+pub struct P384Verifier {
+    key: p384::ecdsa::VerifyingKey,
+}
+
+pub struct Secp256k1Signer {
+    key: k256::ecdsa::SigningKey,
+}
+
+pub struct Secp256k1Verifier {
+    key: k256::ecdsa::VerifyingKey,
+}
+
+pub struct RsaPssSigner {
+    key: BlindedSigningKey<Sha256>,
+}
+
+pub struct RsaPssVerifier {
+    key: RsaVerifyingKey<Sha256>,
+}
+
+pub struct Ed448Signer {
+    key: ed448_goldilocks_plus::SigningKey,
+}
+
+pub struct Ed448Verifier {
+    key: ed448_goldilocks_plus::VerifyingKey,
+}
+
+impl Blake3 {
+    /// Memory-map the file and hash it on all cores, much faster than streaming for large files
+    fn keyed_hash_file(&self, path: impl AsRef<Path>) -> Result<blake3::Hash> {
+        let mut hasher = blake3::Hasher::new_keyed(&self.key);
+        hasher.update_mmap_rayon(path)?;
+        Ok(hasher.finalize())
+    }
+
+    fn sign_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        Ok(self.keyed_hash_file(path)?.as_bytes().to_vec())
+    }
+
+    fn verify_file(&self, path: impl AsRef<Path>, sig: &[u8]) -> Result<bool> {
+        // blake3::Hash compares in constant time
+        Ok(self.keyed_hash_file(path)? == *sig)
+    }
 }
 
 impl TextSign for Blake3 {
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
-        reader.read_to_end(&mut buf)?;
-
-        let hash = blake3::keyed_hash(&self.key, &buf);
-        Ok(hash.as_bytes().to_vec())
+        let mut hasher = blake3::Hasher::new_keyed(&self.key);
+        hasher.update_reader(reader)?;
+        Ok(hasher.finalize().as_bytes().to_vec())
     }
 }
 
 impl TextVerify for Blake3 {
-    fn verify(&self, mut reader: impl Read, sig: &[u8]) -> Result<bool> {
-        let mut buf = Vec::new();
-        reader.read_to_end(&mut buf)?;
-
-        let hash = blake3::keyed_hash(&self.key, &buf);
-        Ok(hash.as_bytes() == sig)
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let mut hasher = blake3::Hasher::new_keyed(&self.key);
+        hasher.update_reader(reader)?;
+        Ok(hasher.finalize() == *sig)
     }
 }
 
 impl KeyLoader for Blake3 {
     fn load(path: impl AsRef<Path>) -> Result<Self> {
-        let key = fs::read(path)?;
-        let key = &key[..32];
-        let key = key.try_into().unwrap();
+        let key = load_key_material(path, BLAKE3_KEY)?;
         Ok(Self { key })
     }
 }
 
 impl KeyGenerator for Blake3 {
     fn generate() -> Result<Vec<Vec<u8>>> {
-        let key = process_gen_pass(32, true, true, true, true)?;
-        let key = key.as_bytes().to_vec();
-        Ok(vec![key])
+        Ok(vec![generate_symmetric_key(BLAKE3_KEY)?])
+    }
+}
+
+impl<M: Mac + KeyInit> TextSign for HmacSigner<M> {
+    fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        let mut mac = <M as KeyInit>::new_from_slice(&self.key)?;
+        for_each_chunk(reader, |chunk| mac.update(chunk))?;
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+}
+
+impl<M: Mac + KeyInit> TextVerify for HmacSigner<M> {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let mut mac = <M as KeyInit>::new_from_slice(&self.key)?;
+        for_each_chunk(reader, |chunk| mac.update(chunk))?;
+        // verify_slice compares in constant time
+        Ok(mac.verify_slice(sig).is_ok())
+    }
+}
+
+impl<M> KeyLoader for HmacSigner<M> {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_secret(path, HMAC_KEY)?;
+        Ok(Self {
+            key,
+            _mac: PhantomData,
+        })
+    }
+}
+
+impl<M> KeyGenerator for HmacSigner<M> {
+    fn generate() -> Result<Vec<Vec<u8>>> {
+        Ok(vec![generate_symmetric_key(HMAC_KEY)?])
     }
 }
 
+// pure Ed25519 hashes the message twice, so it has to be held in memory; use ed25519ph for
+// large inputs
 impl TextSign for Ed25519Signer {
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
@@ -95,10 +256,7 @@ impl TextSign for Ed25519Signer {
 
 impl KeyLoader for Ed25519Signer {
     fn load(path: impl AsRef<Path>) -> Result<Self> {
-        let key = fs::read(path)?;
-        let key = &key[..32];
-        let key = key.try_into().unwrap();
-        let key = SigningKey::from_bytes(&key);
+        let key = Ed25519Key::load(path, false)?.into_signing_key()?;
         Ok(Self { key })
     }
 }
@@ -106,15 +264,23 @@ impl KeyLoader for Ed25519Signer {
 impl KeyGenerator for Ed25519Signer {
     fn generate() -> Result<Vec<Vec<u8>>> {
         let mut csprng = OsRng;
-        let sk = SigningKey::generate(&mut csprng);
-        let pk = sk.verifying_key().to_bytes().to_vec();
-        let sk = sk.to_bytes().to_vec();
+        Self::key_files(&SigningKey::generate(&mut csprng))
+    }
+}
+
+impl Ed25519Signer {
+    /// Secret and public key files for `sk`, sharing the public key's id
+    pub fn key_files(sk: &SigningKey) -> Result<Vec<Vec<u8>>> {
+        let pk = sk.verifying_key().to_bytes();
+        let kid = key_id(&pk);
+        let sk = KeyEnvelope::new(ED25519_SECRET_KEY, &kid, &sk.to_bytes()).to_vec()?;
+        let pk = KeyEnvelope::new(ED25519_PUBLIC_KEY, &kid, &pk).to_vec()?;
         Ok(vec![sk, pk])
     }
 }
 
 impl TextVerify for Ed25519Verifier {
-    fn verify(&self, mut reader: impl Read, sig: &[u8]) -> Result<bool> {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
 
@@ -125,73 +291,532 @@ impl TextVerify for Ed25519Verifier {
 
 impl KeyLoader for Ed25519Verifier {
     fn load(path: impl AsRef<Path>) -> Result<Self> {
-        let key = fs::read(path)?;
-        let key = &key[..32];
-        let key = key.try_into().unwrap();
-        let key = VerifyingKey::from_bytes(&key)?;
+        // a secret key in any format also gives the public key
+        let key = Ed25519Key::load(path, true)?.verifying_key();
+        Ok(Self { key })
+    }
+}
+
+impl TextSign for Ed25519phSigner {
+    fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        let mut prehash = Sha512::new();
+        for_each_chunk(reader, |chunk| prehash.update(chunk))?;
+        let sig = self.key.sign_prehashed(prehash, None)?;
+        Ok(sig.to_bytes().to_vec())
+    }
+}
+
+impl TextVerify for Ed25519phVerifier {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let mut prehash = Sha512::new();
+        for_each_chunk(reader, |chunk| prehash.update(chunk))?;
+        let sig = Signature::from_bytes(sig.try_into()?);
+        Ok(self.key.verify_prehashed(prehash, None, &sig).is_ok())
+    }
+}
+
+// same key files as ed25519, only the signing scheme differs
+impl KeyLoader for Ed25519phSigner {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = Ed25519Signer::load(path)?.key;
+        Ok(Self { key })
+    }
+}
+
+impl KeyLoader for Ed25519phVerifier {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = Ed25519Verifier::load(path)?.key;
+        Ok(Self { key })
+    }
+}
+
+impl KeyGenerator for StreamCipher {
+    fn generate() -> Result<Vec<Vec<u8>>> {
+        Ok(vec![generate_symmetric_key(CHACHA20_KEY)?])
+    }
+}
+
+impl KeyLoader for StreamCipher {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_key_material(path, CHACHA20_KEY)?;
+        Ok(Self {
+            key,
+            source: KeySource::File,
+            cipher: AeadCipher::ChaCha20,
+            aad: Vec::new(),
+        })
+    }
+}
+
+impl StreamCipher {
+    /// Derive a fresh key from the passphrase with a random salt
+    pub fn from_passphrase(passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; PASSPHRASE_SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let source = KeySource::Argon2id {
+            memory: PASSPHRASE_MEMORY,
+            iterations: PASSPHRASE_ITERATIONS,
+            parallelism: PASSPHRASE_PARALLELISM,
+            salt,
+        };
+        Self::for_source(&EncryptionKey::Passphrase(passphrase.to_string()), &source)
+    }
+
+    /// Load or re-derive the key a stream header asks for
+    fn for_source(key: &EncryptionKey, source: &KeySource) -> Result<Self> {
+        match (key, source) {
+            (EncryptionKey::File(path), KeySource::File) => Self::load(path),
+            (
+                EncryptionKey::Passphrase(passphrase),
+                KeySource::Argon2id {
+                    memory,
+                    iterations,
+                    parallelism,
+                    salt,
+                },
+            ) => {
+                if passphrase.is_empty() {
+                    return Err(anyhow!("Passphrase must not be empty"));
+                }
+                let params = argon2::Params::new(*memory, *iterations, *parallelism, Some(32))
+                    .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+                let argon2 = argon2::Argon2::new(
+                    argon2::Algorithm::Argon2id,
+                    argon2::Version::V0x13,
+                    params,
+                );
+                let mut derived = [0u8; 32];
+                argon2
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut derived)
+                    .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+                Ok(Self {
+                    key: derived,
+                    source: source.clone(),
+                    cipher: AeadCipher::ChaCha20,
+                    aad: Vec::new(),
+                })
+            }
+            (_, KeySource::File) => Err(anyhow!("Input was encrypted with a key file, use --key")),
+            _ => Err(anyhow!(
+                "Input was encrypted with a passphrase, use --passphrase"
+            )),
+        }
+    }
+
+    /// The AEAD used when encrypting, decryption follows the stream header
+    pub fn with_cipher(mut self, cipher: AeadCipher) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Associated data that is authenticated but not stored, decryption needs the same bytes
+    pub fn with_aad(mut self, aad: &[u8]) -> Self {
+        self.aad = aad.to_vec();
+        self
+    }
+
+    fn seal<A: Aead + KeyInit>(
+        &self,
+        header: &StreamHeader,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let cipher = A::new_from_slice(&header.stream_key(&self.key))?;
+        encrypt_stream(&cipher, header, &self.aad, reader, writer)
+    }
+
+    fn unseal<A: Aead + KeyInit>(
+        &self,
+        header: &StreamHeader,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        let cipher = A::new_from_slice(&header.stream_key(&self.key))?;
+        decrypt_stream(&cipher, header, &self.aad, reader, writer)
+    }
+
+    fn open(
+        &self,
+        header: &StreamHeader,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        if header.key_source != self.source {
+            return Err(anyhow!("Stream was encrypted with a different key source"));
+        }
+        match AeadCipher::from_id(header.cipher) {
+            Some(AeadCipher::ChaCha20) => self.unseal::<ChaCha20Poly1305>(header, reader, writer),
+            Some(AeadCipher::XChaCha20) => self.unseal::<XChaCha20Poly1305>(header, reader, writer),
+            Some(AeadCipher::Aes256Gcm) => self.unseal::<Aes256Gcm>(header, reader, writer),
+            Some(AeadCipher::Aes256GcmSiv) => self.unseal::<Aes256GcmSiv>(header, reader, writer),
+            None => Err(anyhow!("Unsupported cipher id: {}", header.cipher)),
+        }
+    }
+
+    /// The original single-shot format, ChaCha20-Poly1305 `nonce (12) | ciphertext`
+    fn decrypt_legacy(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if !self.aad.is_empty() {
+            return Err(anyhow!("Legacy ciphertexts have no associated data"));
+        }
+        if data.len() < 12 {
+            return Err(anyhow!("Decryption failed"));
+        }
+        let nonce: &GenericArray<u8, _> = GenericArray::from_slice(&data[..12]);
+        ChaCha20Poly1305::new_from_slice(&self.key)?
+            .decrypt(nonce, &data[12..])
+            .map_err(|_| anyhow!("Decryption failed"))
+    }
+}
+
+impl TextEncrypt for StreamCipher {
+    fn encrypt(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let mut salt = [0u8; STREAM_SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce_prefix = vec![0u8; nonce_prefix_len(self.cipher.nonce_len())];
+        OsRng.fill_bytes(&mut nonce_prefix);
+        let header = StreamHeader {
+            cipher: self.cipher.id(),
+            key_source: self.source.clone(),
+            salt,
+            nonce_prefix,
+        };
+        match self.cipher {
+            AeadCipher::ChaCha20 => self.seal::<ChaCha20Poly1305>(&header, reader, writer),
+            AeadCipher::XChaCha20 => self.seal::<XChaCha20Poly1305>(&header, reader, writer),
+            AeadCipher::Aes256Gcm => self.seal::<Aes256Gcm>(&header, reader, writer),
+            AeadCipher::Aes256GcmSiv => self.seal::<Aes256GcmSiv>(&header, reader, writer),
+        }
+    }
+}
+
+impl TextDecrypt for StreamCipher {
+    fn decrypt(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let header = read_stream_header(reader)?;
+        self.open(&header, reader, writer)
+    }
+}
+
+impl TextSign for P256Signer {
+    fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        let mut digest = Sha256::new();
+        for_each_chunk(reader, |chunk| digest.update(chunk))?;
+
+        let sig: p256::ecdsa::Signature = self.key.try_sign_digest(digest)?;
+        Ok(sig.to_vec())
+    }
+}
+
+impl TextVerify for P256Verifier {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let mut digest = Sha256::new();
+        for_each_chunk(reader, |chunk| digest.update(chunk))?;
+
+        let sig = p256::ecdsa::Signature::from_slice(sig)?;
+        Ok(self.key.verify_digest(digest, &sig).is_ok())
+    }
+}
+
+impl KeyLoader for P256Signer {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(
+            path,
+            "p256 private",
+            p256::ecdsa::SigningKey::from_pkcs8_pem,
+        )?;
+        Ok(Self { key })
+    }
+}
+
+impl KeyLoader for P256Verifier {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(
+            path,
+            "p256 public",
+            p256::ecdsa::VerifyingKey::from_public_key_pem,
+        )?;
+        Ok(Self { key })
+    }
+}
+
+impl KeyGenerator for P256Signer {
+    fn generate() -> Result<Vec<Vec<u8>>> {
+        let sk = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let pk = sk.verifying_key().to_public_key_pem(LineEnding::LF)?;
+        let sk = sk.to_pkcs8_pem(LineEnding::LF)?;
+        Ok(vec![sk.as_bytes().to_vec(), pk.into_bytes()])
+    }
+}
+
+impl TextSign for P384Signer {
+    fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        let mut digest = Sha384::new();
+        for_each_chunk(reader, |chunk| digest.update(chunk))?;
+
+        let sig: p384::ecdsa::Signature = self.key.try_sign_digest(digest)?;
+        Ok(sig.to_vec())
+    }
+}
+
+impl TextVerify for P384Verifier {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let mut digest = Sha384::new();
+        for_each_chunk(reader, |chunk| digest.update(chunk))?;
+
+        let sig = p384::ecdsa::Signature::from_slice(sig)?;
+        Ok(self.key.verify_digest(digest, &sig).is_ok())
+    }
+}
+
+impl KeyLoader for P384Signer {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(
+            path,
+            "p384 private",
+            p384::ecdsa::SigningKey::from_pkcs8_pem,
+        )?;
+        Ok(Self { key })
+    }
+}
+
+impl KeyLoader for P384Verifier {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(
+            path,
+            "p384 public",
+            p384::ecdsa::VerifyingKey::from_public_key_pem,
+        )?;
         Ok(Self { key })
     }
 }
 
-impl KeyGenerator for ChaCha20 {
+impl KeyGenerator for P384Signer {
     fn generate() -> Result<Vec<Vec<u8>>> {
-        let key = process_gen_pass(32, true, true, true, false)?;
-        let key = key.as_bytes().to_vec();
-        Ok(vec![key])
+        let sk = p384::ecdsa::SigningKey::random(&mut OsRng);
+        let pk = sk.verifying_key().to_public_key_pem(LineEnding::LF)?;
+        let sk = sk.to_pkcs8_pem(LineEnding::LF)?;
+        Ok(vec![sk.as_bytes().to_vec(), pk.into_bytes()])
+    }
+}
+
+impl TextSign for Secp256k1Signer {
+    fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        let mut digest = Sha256::new();
+        for_each_chunk(reader, |chunk| digest.update(chunk))?;
+
+        // k256 always produces low-S signatures, as Bitcoin and Ethereum require
+        let sig: k256::ecdsa::Signature = self.key.try_sign_digest(digest)?;
+        Ok(sig.to_vec())
     }
 }
 
-impl KeyLoader for ChaCha20 {
+impl TextVerify for Secp256k1Verifier {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let mut digest = Sha256::new();
+        for_each_chunk(reader, |chunk| digest.update(chunk))?;
+
+        let sig = k256::ecdsa::Signature::from_slice(sig)?;
+        Ok(self.key.verify_digest(digest, &sig).is_ok())
+    }
+}
+
+impl KeyLoader for Secp256k1Signer {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(
+            path,
+            "secp256k1 private",
+            k256::ecdsa::SigningKey::from_pkcs8_pem,
+        )?;
+        Ok(Self { key })
+    }
+}
+
+impl KeyLoader for Secp256k1Verifier {
     fn load(path: impl AsRef<Path>) -> Result<Self> {
-        let key = fs::read(path)?;
-        let key = &key[..32];
-        let key = key.try_into().unwrap();
+        let key = load_pem(
+            path,
+            "secp256k1 public",
+            k256::ecdsa::VerifyingKey::from_public_key_pem,
+        )?;
         Ok(Self { key })
     }
 }
 
-impl TextEncrypt for ChaCha20 {
-    fn encrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+impl KeyGenerator for Secp256k1Signer {
+    fn generate() -> Result<Vec<Vec<u8>>> {
+        let sk = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let pk = sk.verifying_key().to_public_key_pem(LineEnding::LF)?;
+        let sk = sk.to_pkcs8_pem(LineEnding::LF)?;
+        Ok(vec![sk.as_bytes().to_vec(), pk.into_bytes()])
+    }
+}
+
+impl TextSign for RsaPssSigner {
+    fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        let mut digest = Sha256::new();
+        for_each_chunk(reader, |chunk| digest.update(chunk))?;
+
+        let sig = self.key.try_sign_digest_with_rng(&mut OsRng, digest)?;
+        Ok(sig.to_vec())
+    }
+}
+
+impl TextVerify for RsaPssVerifier {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let mut digest = Sha256::new();
+        for_each_chunk(reader, |chunk| digest.update(chunk))?;
+
+        let sig = rsa::pss::Signature::try_from(sig)?;
+        Ok(self.key.verify_digest(digest, &sig).is_ok())
+    }
+}
+
+impl KeyLoader for RsaPssSigner {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(path, "rsa private", RsaPrivateKey::from_pkcs8_pem)?;
+        Ok(Self {
+            key: BlindedSigningKey::new(key),
+        })
+    }
+}
+
+impl KeyLoader for RsaPssVerifier {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(path, "rsa public", RsaPublicKey::from_public_key_pem)?;
+        Ok(Self {
+            key: RsaVerifyingKey::new(key),
+        })
+    }
+}
+
+impl KeyGenerator for RsaPssSigner {
+    fn generate() -> Result<Vec<Vec<u8>>> {
+        let sk = RsaPrivateKey::new(&mut OsRng, RSA_KEY_BITS)?;
+        let pk = sk.to_public_key().to_public_key_pem(LineEnding::LF)?;
+        let sk = sk.to_pkcs8_pem(LineEnding::LF)?;
+        Ok(vec![sk.as_bytes().to_vec(), pk.into_bytes()])
+    }
+}
+
+// like pure Ed25519, Ed448 needs the whole message in memory
+impl TextSign for Ed448Signer {
+    fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
 
-        let cipher = ChaCha20Poly1305::new_from_slice(&self.key)?;
-        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
-        let cipher_text = cipher
-            .encrypt(&nonce, buf.as_ref())
-            .map_err(|_| anyhow!("Encryption failed"))?;
-        let mut result = nonce.to_vec();
-        result.extend_from_slice(&cipher_text);
-        Ok(result)
+        let sig = self.key.sign_raw(&buf);
+        Ok(sig.to_bytes().to_vec())
     }
 }
 
-impl TextDecrypt for ChaCha20 {
-    fn decrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+impl TextVerify for Ed448Verifier {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
 
-        let cipher = ChaCha20Poly1305::new_from_slice(&self.key)?;
-        let nonce: &GenericArray<u8, _> = GenericArray::from_slice(&buf[..12]);
-        let plain = cipher
-            .decrypt(nonce, &buf[12..])
-            .map_err(|_| anyhow!("Decryption failed"))?;
-        Ok(plain)
+        let sig = ed448_goldilocks_plus::Signature::from_slice(sig)
+            .map_err(|e| anyhow!("Invalid Ed448 signature: {:?}", e))?;
+        Ok(self.key.verify_raw(&sig, &buf).is_ok())
+    }
+}
+
+// the ed448 crate's pkcs8 is built without PEM support, so go through DER
+impl KeyLoader for Ed448Signer {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(path, "ed448 private", |pem| {
+            ed448_goldilocks_plus::SigningKey::from_pkcs8_der(&pem_to_der(pem, "PRIVATE KEY")?)
+                .map_err(|e| anyhow!(e.to_string()))
+        })?;
+        Ok(Self { key })
+    }
+}
+
+impl KeyLoader for Ed448Verifier {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = load_pem(path, "ed448 public", |pem| {
+            ed448_goldilocks_plus::VerifyingKey::from_public_key_der(&pem_to_der(
+                pem,
+                "PUBLIC KEY",
+            )?)
+            .map_err(|e| anyhow!(e.to_string()))
+        })?;
+        Ok(Self { key })
+    }
+}
+
+impl KeyGenerator for Ed448Signer {
+    fn generate() -> Result<Vec<Vec<u8>>> {
+        let mut seed = [0u8; ed448_goldilocks_plus::SECRET_KEY_LENGTH];
+        OsRng.fill_bytes(&mut seed);
+        let sk = ed448_goldilocks_plus::SigningKey::try_from(&seed[..])
+            .map_err(|e| anyhow!("Invalid Ed448 key: {:?}", e))?;
+        let pk = sk
+            .verifying_key()
+            .to_public_key_der()
+            .map_err(|e| anyhow!(e.to_string()))?;
+        let sk = sk.to_pkcs8_der().map_err(|e| anyhow!(e.to_string()))?;
+        Ok(vec![
+            der_to_pem(sk.as_bytes(), "PRIVATE KEY")?,
+            der_to_pem(pk.as_bytes(), "PUBLIC KEY")?,
+        ])
     }
 }
 
+/// Read a PKCS#8 or SPKI PEM file, naming the file and the expected key on failure
+fn load_pem<T, E: Display>(
+    path: impl AsRef<Path>,
+    kind: &str,
+    parse: impl FnOnce(&str) -> std::result::Result<T, E>,
+) -> Result<T> {
+    let path = path.as_ref();
+    let pem = fs::read_to_string(path)?;
+    parse(&pem).map_err(|e| anyhow!("Failed to load {} key from {}: {}", kind, path.display(), e))
+}
+
+fn pem_to_der(pem: &str, label: &str) -> Result<Vec<u8>> {
+    let mut der = Vec::new();
+    process_pem_decode(&mut pem.as_bytes(), &mut der, Some(label))?;
+    Ok(der)
+}
+
+fn der_to_pem(der: &[u8], label: &str) -> Result<Vec<u8>> {
+    let mut pem = Vec::new();
+    process_pem_encode(&mut &der[..], &mut pem, label)?;
+    Ok(pem)
+}
+
+/// 256 bits from the OS RNG, wrapped in a key envelope
+fn generate_symmetric_key(algorithm: &str) -> Result<Vec<u8>> {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    KeyEnvelope::new(algorithm, &key_id(&key), &key).to_vec()
+}
+
 pub fn process_text_sign(input: &str, key: &str, format: TextSignFormat) -> Result<String> {
     let mut reader = get_reader(input)?;
 
     let signed = match format {
         TextSignFormat::Blake3 => {
             let signer = Blake3::load(key)?;
-            signer.sign(&mut reader)?
+            if input == "-" {
+                signer.sign(&mut reader)?
+            } else {
+                signer.sign_file(input)?
+            }
         }
         TextSignFormat::Ed25519 => {
             let signer = Ed25519Signer::load(key)?;
             signer.sign(&mut reader)?
         }
+        TextSignFormat::Ed25519ph => Ed25519phSigner::load(key)?.sign(&mut reader)?,
+        TextSignFormat::HmacSha256 => HmacSha256::load(key)?.sign(&mut reader)?,
+        TextSignFormat::HmacSha384 => HmacSha384::load(key)?.sign(&mut reader)?,
+        TextSignFormat::HmacSha512 => HmacSha512::load(key)?.sign(&mut reader)?,
+        TextSignFormat::HmacSha3_256 => HmacSha3_256::load(key)?.sign(&mut reader)?,
+        TextSignFormat::P256 => P256Signer::load(key)?.sign(&mut reader)?,
+        TextSignFormat::P384 => P384Signer::load(key)?.sign(&mut reader)?,
+        TextSignFormat::Secp256k1 => Secp256k1Signer::load(key)?.sign(&mut reader)?,
+        TextSignFormat::RsaPss => RsaPssSigner::load(key)?.sign(&mut reader)?,
+        TextSignFormat::Ed448 => Ed448Signer::load(key)?.sign(&mut reader)?,
         _ => return Err(anyhow!("Unsupported format")),
     };
 
@@ -207,16 +832,31 @@ pub fn process_text_verify(
     format: TextSignFormat,
 ) -> Result<bool> {
     let mut reader = get_reader(input)?;
+    let sig = BASE64_URL_SAFE_NO_PAD.decode(sig)?;
 
     let valid = match format {
         TextSignFormat::Blake3 => {
             let verifier = Blake3::load(key)?;
-            verifier.verify(&mut reader, &BASE64_URL_SAFE_NO_PAD.decode(sig)?)?
+            if input == "-" {
+                verifier.verify(&mut reader, &sig)?
+            } else {
+                verifier.verify_file(input, &sig)?
+            }
         }
         TextSignFormat::Ed25519 => {
             let verifier = Ed25519Verifier::load(key)?;
-            verifier.verify(&mut reader, &BASE64_URL_SAFE_NO_PAD.decode(sig)?)?
+            verifier.verify(&mut reader, &sig)?
         }
+        TextSignFormat::Ed25519ph => Ed25519phVerifier::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::HmacSha256 => HmacSha256::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::HmacSha384 => HmacSha384::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::HmacSha512 => HmacSha512::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::HmacSha3_256 => HmacSha3_256::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::P256 => P256Verifier::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::P384 => P384Verifier::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::Secp256k1 => Secp256k1Verifier::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::RsaPss => RsaPssVerifier::load(key)?.verify(&mut reader, &sig)?,
+        TextSignFormat::Ed448 => Ed448Verifier::load(key)?.verify(&mut reader, &sig)?,
         _ => return Err(anyhow!("Unsupported format")),
     };
 
@@ -226,30 +866,105 @@ pub fn process_text_verify(
 pub fn process_text_generate(format: TextSignFormat) -> Result<Vec<Vec<u8>>> {
     match format {
         TextSignFormat::Blake3 => Blake3::generate(),
-        TextSignFormat::Ed25519 => Ed25519Signer::generate(),
-        TextSignFormat::Chacha => ChaCha20::generate(),
+        TextSignFormat::Ed25519 | TextSignFormat::Ed25519ph => Ed25519Signer::generate(),
+        TextSignFormat::Chacha => StreamCipher::generate(),
+        TextSignFormat::HmacSha256 => HmacSha256::generate(),
+        TextSignFormat::HmacSha384 => HmacSha384::generate(),
+        TextSignFormat::HmacSha512 => HmacSha512::generate(),
+        TextSignFormat::HmacSha3_256 => HmacSha3_256::generate(),
+        TextSignFormat::P256 => P256Signer::generate(),
+        TextSignFormat::P384 => P384Signer::generate(),
+        TextSignFormat::Secp256k1 => Secp256k1Signer::generate(),
+        TextSignFormat::RsaPss => RsaPssSigner::generate(),
+        TextSignFormat::Ed448 => Ed448Signer::generate(),
+        TextSignFormat::X25519 => AgeIdentity::generate(),
     }
 }
 
-pub fn process_text_encrypt(input: &str, key: &str) -> Result<String> {
-    let mut reader = get_reader(input)?;
-
-    let chacha = ChaCha20::load(key)?;
-    let result = chacha.encrypt(&mut reader)?;
+fn read_stream_header(reader: &mut dyn Read) -> Result<StreamHeader> {
+    StreamHeader::read_from(reader, |id| AeadCipher::from_id(id).map(|c| c.nonce_len()))
+}
 
-    Ok(BASE64_URL_SAFE_NO_PAD.encode(result))
+/// Encrypt to a raw chunked stream, or to base64url text when `armor` is set
+pub fn process_text_encrypt(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    key: &EncryptionKey,
+    cipher: AeadCipher,
+    aad: &[u8],
+    armor: bool,
+) -> Result<()> {
+    let stream = match key {
+        EncryptionKey::File(path) => StreamCipher::load(path)?,
+        EncryptionKey::Passphrase(passphrase) => StreamCipher::from_passphrase(passphrase)?,
+        _ => anyhow::bail!("age recipients only work with the age format"),
+    }
+    .with_cipher(cipher)
+    .with_aad(aad);
+    if armor {
+        let mut encoder = base64::write::EncoderWriter::new(&mut *writer, &BASE64_URL_SAFE_NO_PAD);
+        stream.encrypt(reader, &mut encoder)?;
+        let writer = encoder.finish()?;
+        writeln!(writer)?;
+        writer.flush()?;
+        Ok(())
+    } else {
+        stream.encrypt(reader, writer)
+    }
 }
 
-pub fn process_text_decrypt(input: &str, key: &str) -> Result<String> {
-    let mut reader = get_reader(input)?;
-    let mut buf = Vec::new();
-    reader.read_to_end(&mut buf)?;
-    let nonce_and_cipher = BASE64_URL_SAFE_NO_PAD.decode(buf)?;
+/// Decrypt a raw or armored stream, an age file, or the legacy base64url `nonce | ciphertext`
+/// output
+pub fn process_text_decrypt(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    key: &EncryptionKey,
+    aad: &[u8],
+) -> Result<()> {
+    let mut magic = [0u8; 8];
+    let n = read_full(reader, &mut magic)?;
+    let mut reader = (&magic[..n]).chain(reader);
+    if magic[..n] == STREAM_MAGIC[..] {
+        return decrypt_with(&mut reader, writer, key, aad);
+    }
+    let age = magic[..n] == AGE_MAGIC.as_bytes()[..8];
+    if age || &magic[..n] == b"-----BEG" {
+        if !aad.is_empty() {
+            anyhow::bail!("age files do not support associated data");
+        }
+        return process_age_decrypt(&mut reader, writer, key, !age);
+    }
 
-    let chacha = ChaCha20::load(key)?;
-    let plaintext = chacha.decrypt(&mut &nonce_and_cipher[..])?;
+    let mut decoded = base64_auto_reader(reader);
+    let mut magic = [0u8; 8];
+    let n = read_full(&mut decoded, &mut magic)?;
+    let mut decoded = (&magic[..n]).chain(decoded);
+    if magic[..n] == STREAM_MAGIC[..] {
+        return decrypt_with(&mut decoded, writer, key, aad);
+    }
 
-    Ok(String::from_utf8(plaintext)?)
+    let EncryptionKey::File(path) = key else {
+        return Err(anyhow!("Input was encrypted with a key file, use --key"));
+    };
+    let mut data = Vec::new();
+    decoded.read_to_end(&mut data)?;
+    let stream = StreamCipher::load(path)?.with_aad(aad);
+    writer.write_all(&stream.decrypt_legacy(&data)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The key (or the salt to derive it) is only known once the header is read
+fn decrypt_with(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    key: &EncryptionKey,
+    aad: &[u8],
+) -> Result<()> {
+    let header = read_stream_header(reader)?;
+    StreamCipher::for_source(key, &header.key_source)?
+        .with_aad(aad)
+        .open(&header, reader, writer)
 }
 
 #[cfg(test)]
@@ -277,13 +992,348 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_ed25519ph_rfc8032_vector() -> Result<()> {
+        // RFC 8032 7.3, message "abc"
+        let signer = Ed25519phSigner {
+            key: SigningKey::from_bytes(
+                &hex("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42")
+                    .try_into()
+                    .unwrap(),
+            ),
+        };
+        let verifier = Ed25519phVerifier {
+            key: signer.key.verifying_key(),
+        };
+        let sig = signer.sign(&mut &b"abc"[..])?;
+        assert_eq!(
+            sig,
+            hex(concat!(
+                "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41",
+                "31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406"
+            ))
+        );
+        assert!(verifier.verify(&mut &b"abc"[..], &sig)?);
+        assert!(!verifier.verify(&mut &b"abd"[..], &sig)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming_sign_large_input() -> Result<()> {
+        // larger than the chunk size and than one BLAKE3 rayon split
+        let data: Vec<u8> = (0..3 * 1024 * 1024 + 7).map(|i| i as u8).collect();
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("input.bin");
+        std::fs::write(&input, &data)?;
+
+        let blake3 = Blake3::load("fixtures/blake3.key")?;
+        let expected = blake3::keyed_hash(&blake3.key, &data);
+        assert_eq!(blake3.sign(&mut &data[..])?, expected.as_bytes());
+        assert_eq!(blake3.sign_file(&input)?, expected.as_bytes());
+        assert!(blake3.verify_file(&input, expected.as_bytes())?);
+
+        let signer = Ed25519phSigner::load("fixtures/ed25519.sk")?;
+        let verifier = Ed25519phVerifier::load("fixtures/ed25519.pk")?;
+        let sig = signer.sign(&mut std::fs::File::open(&input)?)?;
+        assert!(verifier.verify(&mut &data[..], &sig)?);
+        // ed25519ph signatures are not interchangeable with pure ed25519 ones
+        assert!(!Ed25519Verifier::load("fixtures/ed25519.pk")?.verify(&mut &data[..], &sig)?);
+        Ok(())
+    }
+
     #[test]
     fn test_chacha20_encrypt_decrypt() -> Result<()> {
-        let chacha = ChaCha20::load("fixtures/chacha.key")?;
+        let chacha = StreamCipher::load("fixtures/chacha.key")?;
         let data = b"hello world!";
-        let cipher = chacha.encrypt(&mut &data[..])?;
-        let plain = chacha.decrypt(&mut &cipher[..])?;
+        let mut cipher = Vec::new();
+        chacha.encrypt(&mut &data[..], &mut cipher)?;
+        let mut plain = Vec::new();
+        chacha.decrypt(&mut &cipher[..], &mut plain)?;
         assert_eq!(data.to_vec(), plain);
         Ok(())
     }
+
+    #[test]
+    fn test_text_encrypt_decrypt_armor_and_legacy() -> Result<()> {
+        let key = EncryptionKey::File("fixtures/chacha.key".to_string());
+        let data: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        for armor in [false, true] {
+            let mut encrypted = Vec::new();
+            process_text_encrypt(
+                &mut &data[..],
+                &mut encrypted,
+                &key,
+                AeadCipher::ChaCha20,
+                b"",
+                armor,
+            )?;
+            assert_eq!(encrypted.starts_with(STREAM_MAGIC), !armor);
+            let mut decrypted = Vec::new();
+            process_text_decrypt(&mut &encrypted[..], &mut decrypted, &key, b"")?;
+            assert_eq!(decrypted, data);
+        }
+
+        // base64url of nonce || ciphertext, as written before the chunked format
+        let chacha =
+            ChaCha20Poly1305::new_from_slice(&StreamCipher::load("fixtures/chacha.key")?.key)?;
+        let nonce = [9u8; 12];
+        let mut legacy = nonce.to_vec();
+        legacy.extend(
+            chacha
+                .encrypt(GenericArray::from_slice(&nonce), &b"hello world!"[..])
+                .unwrap(),
+        );
+        let legacy = BASE64_URL_SAFE_NO_PAD.encode(legacy);
+        let mut decrypted = Vec::new();
+        process_text_decrypt(&mut legacy.as_bytes(), &mut decrypted, &key, b"")?;
+        assert_eq!(decrypted, b"hello world!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_encrypt_ciphers_and_aad() -> Result<()> {
+        let key = EncryptionKey::File("fixtures/chacha.key".to_string());
+        let data = b"release-2024.tar.gz contents";
+        for cipher in [
+            AeadCipher::ChaCha20,
+            AeadCipher::XChaCha20,
+            AeadCipher::Aes256Gcm,
+            AeadCipher::Aes256GcmSiv,
+        ] {
+            let mut encrypted = Vec::new();
+            process_text_encrypt(&mut &data[..], &mut encrypted, &key, cipher, b"v1", false)?;
+            let header = read_stream_header(&mut &encrypted[..])?;
+            assert_eq!(header.cipher, cipher.id());
+            assert_eq!(header.nonce_prefix.len(), cipher.nonce_len() - 5);
+
+            let mut decrypted = Vec::new();
+            process_text_decrypt(&mut &encrypted[..], &mut decrypted, &key, b"v1")?;
+            assert_eq!(decrypted, data, "{}", cipher);
+            for aad in [&b""[..], b"v2"] {
+                let result = process_text_decrypt(&mut &encrypted[..], &mut Vec::new(), &key, aad);
+                assert!(result.is_err(), "{} accepted the wrong aad", cipher);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_passphrase_encrypt_decrypt() -> Result<()> {
+        // cheap parameters keep the test fast, decryption takes them from the header
+        let passphrase = EncryptionKey::Passphrase("correct horse battery staple".to_string());
+        let source = KeySource::Argon2id {
+            memory: 64,
+            iterations: 1,
+            parallelism: 1,
+            salt: [5u8; PASSPHRASE_SALT_LEN],
+        };
+        let mut encrypted = Vec::new();
+        StreamCipher::for_source(&passphrase, &source)?
+            .encrypt(&mut &b"hello world!"[..], &mut encrypted)?;
+        assert_eq!(read_stream_header(&mut &encrypted[..])?.key_source, source);
+
+        let mut decrypted = Vec::new();
+        process_text_decrypt(&mut &encrypted[..], &mut decrypted, &passphrase, b"")?;
+        assert_eq!(decrypted, b"hello world!");
+
+        let wrong = EncryptionKey::Passphrase("incorrect horse".to_string());
+        assert!(process_text_decrypt(&mut &encrypted[..], &mut Vec::new(), &wrong, b"").is_err());
+        let file = EncryptionKey::File("fixtures/chacha.key".to_string());
+        assert!(process_text_decrypt(&mut &encrypted[..], &mut Vec::new(), &file, b"").is_err());
+        Ok(())
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        data_encoding::HEXLOWER_PERMISSIVE
+            .decode(s.as_bytes())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hmac_matches_openssl() -> Result<()> {
+        // printf 'hello world!' | openssl dgst -<digest> -hmac "$(cat fixtures/hmac.key)" -binary
+        let key = "fixtures/hmac.key";
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("input.txt");
+        std::fs::write(&input, b"hello world!")?;
+        let input = input.to_str().unwrap();
+
+        for (format, expected) in [
+            (
+                TextSignFormat::HmacSha256,
+                "p259zFoXvk-XCFi_qhijtj7DxVuWEjHzH9k6UhZl4eI",
+            ),
+            (
+                TextSignFormat::HmacSha384,
+                "SEsUlXMTGcvM1A5t_9R0mMsl0SGbso_ZZBBEBJhFZiqD16b4EqEGmGyY7T60diDs",
+            ),
+            (
+                TextSignFormat::HmacSha512,
+                "ZnI31-hQaTfxI9wuXl1d_b7lZudicPrFsgXOLko70yPHghMDElvDm9ORbjZrY_NsOSthU-PlAuznjlvGKqrynQ",
+            ),
+            (
+                TextSignFormat::HmacSha3_256,
+                "BF-g5aROwON-v9fxq6bNQpgtFs36fpbiVSQTTfcLDi0",
+            ),
+        ] {
+            assert_eq!(process_text_sign(input, key, format)?, expected, "{}", format);
+            assert!(process_text_verify(input, key, expected, format)?);
+            assert!(!process_text_verify(input, key, &expected[4..], format)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ecdsa_rfc6979_vectors() -> Result<()> {
+        // RFC 6979 A.2.5, P-256 with SHA-256, message "sample"
+        let signer = P256Signer {
+            key: p256::ecdsa::SigningKey::from_slice(&hex(
+                "C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721",
+            ))?,
+        };
+        assert_eq!(
+            signer.sign(&mut &b"sample"[..])?,
+            hex(concat!(
+                "EFD48B2AACB6A8FD1140DD9CD45E81D69D2C877B56AAF991C34D0EA84EAF3716",
+                "F7CB1C942D657C41D436C7A1B6E29F65F3E900DBB9AFF4064DC4AB2F843ACDA8"
+            ))
+        );
+
+        // RFC 6979 A.2.6, P-384 with SHA-384, message "sample"
+        let signer = P384Signer {
+            key: p384::ecdsa::SigningKey::from_slice(&hex(concat!(
+                "6B9D3DAD2E1B8C1C05B19875B6659F4DE23C3B667BF297BA",
+                "9AA47740787137D896D5724E4C70A825F872C9EA60D2EDF5"
+            )))?,
+        };
+        assert_eq!(
+            signer.sign(&mut &b"sample"[..])?,
+            hex(concat!(
+                "94EDBB92A5ECB8AAD4736E56C691916B3F88140666CE9FA7",
+                "3D64C4EA95AD133C81A648152E44ACF96E36DD1E80FABE46",
+                "99EF4AEB15F178CEA1FE40DB2603138F130E740A19624526",
+                "203B6351D0A3A94FA329C145786E679E7B82C71A38628AC8"
+            ))
+        );
+
+        // widely used secp256k1 RFC 6979 vector: private key 1, "Satoshi Nakamoto"
+        let mut sk = [0u8; 32];
+        sk[31] = 1;
+        let signer = Secp256k1Signer {
+            key: k256::ecdsa::SigningKey::from_slice(&sk)?,
+        };
+        assert_eq!(
+            signer.sign(&mut &b"Satoshi Nakamoto"[..])?,
+            hex(concat!(
+                "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8",
+                "2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5"
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ed448_rfc8032_vector() -> Result<()> {
+        // RFC 8032 7.4, "1 octet"
+        let signer = Ed448Signer {
+            key: ed448_goldilocks_plus::SigningKey::try_from(
+                &hex(concat!(
+                    "c4eab05d357007c632f3dbb48489924d552b08fe0c353a0d4a",
+                    "1f00acda2c463afbea67c5e8d2877c5e3bc397a659949ef802",
+                    "1e954e0a12274e"
+                ))[..],
+            )
+            .unwrap(),
+        };
+        let verifier = Ed448Verifier {
+            key: signer.key.verifying_key(),
+        };
+        assert_eq!(
+            verifier.key.to_bytes().to_vec(),
+            hex(concat!(
+                "43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372b",
+                "fa0c6c6798c0866aea01eb00742802b8438ea4cb82169c2351",
+                "60627b4c3a9480"
+            ))
+        );
+        let sig = signer.sign(&mut &[0x03u8][..])?;
+        assert_eq!(
+            sig,
+            hex(concat!(
+                "26b8f91727bd62897af15e41eb43c377efb9c610d48f2335cb",
+                "0bd0087810f4352541b143c4b981b7e18f62de8ccdf633fc1b",
+                "f037ab7cd779805e0dbcc0aae1cbcee1afb2e027df36bc04dc",
+                "ecbf154336c19f0af7e0a6472905e799f1953d2a0ff3348ab2",
+                "1aa4adafd1d234441cf807c03a00"
+            ))
+        );
+        assert!(verifier.verify(&mut &[0x03u8][..], &sig)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rsa_pss_openssl_signature() -> Result<()> {
+        // openssl dgst -sha256 -sigopt rsa_padding_mode:pss -sigopt rsa_pss_saltlen:32 -sign rsa-pss.sk
+        let sig = BASE64_STANDARD.decode(concat!(
+            "p00CyFQj9jn64/SL8YLGsWdiHZeno1r92kxLREQe9MX/RAox3grm/L8dIKhh+n8RVYBf2yKPG4cQ1z9w",
+            "+NIgN1Tcs+PCUi8gtCKI2QBpznZ64dgtBEvo/4/q2OREfWMYYIWdvz5vCax937CMKLDZk+FpHkgPQIQb",
+            "wFWbpRDCwXp1Fs6sge3R0Vz8obaXw/kRMQgsXvmCwBkW5G8EALL6k4h2PpP9APqYLuqMlXZZXfPAY5uc",
+            "ALsVb7MDIiRJfuRQ0SricNk8n9YHGmT17Vt5UNs2InFfjtIQCIGOOVVEAl3DZJ7bzuXuShLCNMPta0aY",
+            "uDQQ1Pp4iFi+pLqO4o/+0Q=="
+        ))?;
+        let verifier = RsaPssVerifier::load("fixtures/rsa-pss.pk")?;
+        assert!(verifier.verify(&mut &b"hello world!"[..], &sig)?);
+        assert!(!verifier.verify(&mut &b"hello world?"[..], &sig)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pem_key_fixtures_sign_verify() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("input.txt");
+        std::fs::write(&input, b"hello world!")?;
+        let input = input.to_str().unwrap();
+        for format in [
+            TextSignFormat::P256,
+            TextSignFormat::P384,
+            TextSignFormat::Secp256k1,
+            TextSignFormat::RsaPss,
+            TextSignFormat::Ed448,
+        ] {
+            let (sk, pk) = (
+                format!("fixtures/{}.sk", format),
+                format!("fixtures/{}.pk", format),
+            );
+            let sig = process_text_sign(input, &sk, format)?;
+            assert!(process_text_verify(input, &pk, &sig, format)?, "{}", format);
+            // a public key is not a private key
+            assert!(process_text_sign(input, &pk, format).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_generated_keys_load() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (sk, pk, key) = (
+            dir.path().join("gen.sk"),
+            dir.path().join("gen.pk"),
+            dir.path().join("gen_blake3.key"),
+        );
+        let keys = process_text_generate(TextSignFormat::Ed25519)?;
+        std::fs::write(&sk, &keys[0])?;
+        std::fs::write(&pk, &keys[1])?;
+
+        let signer = Ed25519Signer::load(&sk)?;
+        let verifier = Ed25519Verifier::load(&pk)?;
+        let sig = signer.sign(&mut &b"hello"[..])?;
+        assert!(verifier.verify(&mut &b"hello"[..], &sig)?);
+        assert!(Ed25519Signer::load(&pk).is_err());
+
+        let keys = process_text_generate(TextSignFormat::Blake3)?;
+        std::fs::write(&key, &keys[0])?;
+        assert!(Blake3::load(&key).is_ok());
+        assert!(StreamCipher::load(&key).is_err());
+        Ok(())
+    }
 }
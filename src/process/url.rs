@@ -0,0 +1,184 @@
+use crate::command::UrlFormat;
+use anyhow::Result;
+use percent_encoding::{percent_decode, percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde_json::{Map, Value};
+
+/// RFC 3986 unreserved characters are the only ones left alone in a component
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+/// pchar plus "/": unreserved, sub-delims, ":" and "@"
+const PATH: &AsciiSet = &COMPONENT
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@')
+    .remove(b'/');
+/// pchar plus "/" and "?", but "+" is escaped since form decoders read it as a space
+const QUERY: &AsciiSet = &PATH.add(b'+').remove(b'?');
+
+pub fn process_url_encode(input: &[u8], format: UrlFormat) -> Result<String> {
+    let encoded = match format {
+        UrlFormat::Component => percent_encode(input, COMPONENT).to_string(),
+        UrlFormat::Path => percent_encode(input, PATH).to_string(),
+        UrlFormat::Query => percent_encode(input, QUERY).to_string(),
+        UrlFormat::Form => encode_form(&serde_json::from_slice(input)?)?,
+        UrlFormat::Html => html_escape::encode_safe(std::str::from_utf8(input)?).into_owned(),
+        UrlFormat::QuotedPrintable => quoted_printable::encode_to_str(input),
+        UrlFormat::Idna => idna::domain_to_ascii(std::str::from_utf8(input)?)?,
+    };
+    Ok(encoded)
+}
+
+pub fn process_url_decode(input: &str, format: UrlFormat) -> Result<Vec<u8>> {
+    let decoded = match format {
+        UrlFormat::Component | UrlFormat::Path | UrlFormat::Query => {
+            percent_decode(input.as_bytes()).collect()
+        }
+        UrlFormat::Form => serde_json::to_vec_pretty(&decode_form(input))?,
+        UrlFormat::Html => html_escape::decode_html_entities(input)
+            .into_owned()
+            .into_bytes(),
+        UrlFormat::QuotedPrintable => {
+            quoted_printable::decode(input, quoted_printable::ParseMode::Robust)?
+        }
+        UrlFormat::Idna => {
+            let (domain, result) = idna::domain_to_unicode(input);
+            result?;
+            domain.into_bytes()
+        }
+    };
+    Ok(decoded)
+}
+
+/// Build `application/x-www-form-urlencoded` from a flat JSON object, arrays become repeated keys
+fn encode_form(value: &Value) -> Result<String> {
+    let Value::Object(map) = value else {
+        anyhow::bail!("Form data must be a JSON object");
+    };
+
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in map {
+        match value {
+            Value::Array(values) => {
+                for value in values {
+                    serializer.append_pair(key, &form_value(key, value)?);
+                }
+            }
+            value => {
+                serializer.append_pair(key, &form_value(key, value)?);
+            }
+        }
+    }
+    Ok(serializer.finish())
+}
+
+fn form_value(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Null => Ok(String::new()),
+        Value::Bool(_) | Value::Number(_) => Ok(value.to_string()),
+        Value::Array(_) | Value::Object(_) => {
+            anyhow::bail!("Nested value for {} is not supported", key)
+        }
+    }
+}
+
+/// Parse form data into a JSON object, collecting repeated keys into arrays
+fn decode_form(input: &str) -> Value {
+    let mut map = Map::new();
+    for (key, value) in form_urlencoded::parse(input.trim_start_matches('?').as_bytes()) {
+        let value = Value::String(value.into_owned());
+        match map.get_mut(key.as_ref()) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+            None => {
+                map.insert(key.into_owned(), value);
+            }
+        }
+    }
+    Value::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_encoding_sets() -> Result<()> {
+        let input = b"a b/c?d=e&f+g~h";
+        assert_eq!(
+            process_url_encode(input, UrlFormat::Component)?,
+            "a%20b%2Fc%3Fd%3De%26f%2Bg~h"
+        );
+        assert_eq!(
+            process_url_encode(input, UrlFormat::Path)?,
+            "a%20b/c%3Fd=e&f+g~h"
+        );
+        assert_eq!(
+            process_url_encode(input, UrlFormat::Query)?,
+            "a%20b/c?d=e&f%2Bg~h"
+        );
+        assert_eq!(
+            process_url_decode("caf%C3%A9%20%2B", UrlFormat::Component)?,
+            "café +".as_bytes()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_form_json_roundtrip() -> Result<()> {
+        let json = br#"{"q":"rust lang","tag":["a","b&c"],"page":2}"#;
+        let form = process_url_encode(json, UrlFormat::Form)?;
+        assert_eq!(form, "page=2&q=rust+lang&tag=a&tag=b%26c");
+
+        let decoded: Value = serde_json::from_slice(&process_url_decode(&form, UrlFormat::Form)?)?;
+        assert_eq!(
+            decoded,
+            serde_json::json!({"q": "rust lang", "tag": ["a", "b&c"], "page": "2"})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_html_qp_idna() -> Result<()> {
+        assert_eq!(
+            process_url_encode(b"<a href=\"x\">Tom & Jerry</a>", UrlFormat::Html)?,
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&lt;&#x2F;a&gt;"
+        );
+        assert_eq!(
+            process_url_decode("&copy; &eacute;t&#233; &#x1F600;", UrlFormat::Html)?,
+            "© été 😀".as_bytes()
+        );
+
+        assert_eq!(
+            process_url_encode("café=".as_bytes(), UrlFormat::QuotedPrintable)?,
+            "caf=C3=A9=3D"
+        );
+        assert_eq!(
+            process_url_decode("caf=C3=A9 soft=\r\nbreak", UrlFormat::QuotedPrintable)?,
+            "café softbreak".as_bytes()
+        );
+
+        assert_eq!(
+            process_url_encode("bücher.example".as_bytes(), UrlFormat::Idna)?,
+            "xn--bcher-kva.example"
+        );
+        assert_eq!(
+            process_url_decode("xn--bcher-kva.example", UrlFormat::Idna)?,
+            "bücher.example".as_bytes()
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use std::{fs::File, io::Read};
+use std::{
+    fs::File,
+    io::{self, BufWriter, IsTerminal, Read, Write},
+};
 
 pub fn get_reader(input: &str) -> anyhow::Result<Box<dyn Read>> {
     let reader: Box<dyn Read> = if input == "-" {
@@ -8,3 +11,121 @@ pub fn get_reader(input: &str) -> anyhow::Result<Box<dyn Read>> {
     };
     Ok(reader)
 }
+
+pub fn get_writer(output: &str) -> anyhow::Result<Box<dyn Write>> {
+    let writer: Box<dyn Write> = if output == "-" {
+        Box::new(BufWriter::new(std::io::stdout()))
+    } else {
+        Box::new(BufWriter::new(File::create(output)?))
+    };
+    Ok(writer)
+}
+
+/// Like `get_writer`, but refuses to dump binary data to an interactive terminal unless forced
+pub fn get_binary_writer(output: &str, force: bool) -> anyhow::Result<Box<dyn Write>> {
+    let writer = get_writer(output)?;
+    if output == "-" && !force && std::io::stdout().is_terminal() {
+        return Ok(Box::new(BinaryGuard::new(writer)));
+    }
+    Ok(writer)
+}
+
+/// Fails the write as soon as the data stops looking like text
+pub struct BinaryGuard<W: Write> {
+    inner: W,
+    // trailing bytes of an incomplete UTF-8 sequence from the previous write
+    pending: Vec<u8>,
+}
+
+impl<W: Write> BinaryGuard<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+        }
+    }
+
+    fn check(&mut self, buf: &[u8]) -> io::Result<()> {
+        let is_control =
+            |c: &u8| c.is_ascii_control() && !matches!(c, b'\t' | b'\n' | b'\r' | 0x0c);
+        let mut data = std::mem::take(&mut self.pending);
+        data.extend_from_slice(buf);
+
+        let binary = match std::str::from_utf8(&data) {
+            Ok(_) => data.iter().any(is_control),
+            Err(e) if e.error_len().is_none() => {
+                self.pending = data[e.valid_up_to()..].to_vec();
+                data[..e.valid_up_to()].iter().any(is_control)
+            }
+            Err(_) => true,
+        };
+
+        if binary {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "refusing to write binary data to a terminal, use -o FILE or --force",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BinaryGuard<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // the whole buffer has been checked, so it has to be written in full: a short write
+        // would make the caller check the rest again behind the pending bytes
+        self.check(buf)?;
+        self.inner.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_guard() {
+        let mut guard = BinaryGuard::new(Vec::new());
+        assert!(guard.write_all("hello wörld\n".as_bytes()).is_ok());
+        // a multi-byte character split across writes is still text
+        let bytes = "ö".as_bytes();
+        assert!(guard.write_all(&bytes[..1]).is_ok());
+        assert!(guard.write_all(&bytes[1..]).is_ok());
+
+        let key = std::fs::read("fixtures/ed25519.sk").unwrap();
+        assert!(BinaryGuard::new(Vec::new()).write_all(&key).is_err());
+        assert!(BinaryGuard::new(Vec::new()).write_all(b"\x00\x01").is_err());
+        // escape sequences must not reach the terminal either
+        assert!(BinaryGuard::new(Vec::new())
+            .write_all(b"\x1b]0;title\x07")
+            .is_err());
+    }
+
+    /// Accepts at most one byte per write, like a slow pipe
+    struct Trickle(Vec<u8>);
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.extend_from_slice(&buf[..buf.len().min(1)]);
+            Ok(buf.len().min(1))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_binary_guard_short_writes() {
+        let text = "ends with a split ö".as_bytes();
+        let mut guard = BinaryGuard::new(Trickle(Vec::new()));
+        guard.write_all(&text[..text.len() - 1]).unwrap();
+        guard.write_all(&text[text.len() - 1..]).unwrap();
+        assert_eq!(guard.inner.0, text);
+    }
+}